@@ -0,0 +1,46 @@
+//! Measures how long a fixed loopback range takes to scan at several concurrency levels.
+//! Every port in the range is unbound, so the numbers reflect dispatch and worker overhead
+//! rather than banner reads.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use night_tool::scanner::{scan_range, ScanConfig, ScanEvent};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+
+const START: u16 = 40000;
+const END: u16 = 44999;
+
+async fn scan_once(concurrency: usize) -> usize {
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(2048);
+    let drain = tokio::spawn(async move {
+        let mut n = 0usize;
+        while let Some(event) = rx.recv().await {
+            match event {
+                ScanEvent::Result(_) => n += 1,
+                ScanEvent::Finished { .. } => break,
+                _ => {}
+            }
+        }
+        n
+    });
+
+    let config = ScanConfig { concurrency: Some(concurrency), ..Default::default() };
+    scan_range("127.0.0.1", START, END, &config, tx).await;
+    drain.await.unwrap_or(0)
+}
+
+fn bench_scan_range(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("scan_range_loopback_5000");
+    group.sample_size(10);
+
+    for concurrency in [64usize, 256, 1024] {
+        group.bench_with_input(BenchmarkId::from_parameter(concurrency), &concurrency, |b, &n| {
+            b.to_async(&rt).iter(|| scan_once(n));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_scan_range);
+criterion_main!(benches);
@@ -0,0 +1,453 @@
+//! Command-line interface for the Night Tool application.
+//! Without arguments the interactive TUI is launched; with `--no-ui` the scan runs
+//! headless and results are printed to stdout.
+//! # Structs
+//! - `Cli` - Parsed command-line arguments.
+//! # Enums
+//! - `OutputFormat` - Result output of headless mode: text lines or JSON Lines.
+//! # Functions
+//! - `run_headless(cli: Cli, profile: Option<Profile>)` - Runs a scan without the TUI and prints results as they arrive.
+//! - `ping_method_for(host: &str, config: &ScanConfig)` - How dead hosts will be detected, when `skip_dead_hosts` is set.
+//! # Examples
+//! ```text
+//! night_tool --no-ui --host 10.0.0.1 --start 1 --end 1024
+//! night_tool --no-ui --host 10.0.0.1 --ports 22,80,443,8000-8100
+//! night_tool --no-ui --quiet --host 10.0.0.1 --top | xargs -n1 echo
+//! night_tool --no-ui --profile web-servers
+//! night_tool --no-ui --json --host 10.0.0.1 --top > results.jsonl
+//! night_tool --no-ui --format jsonl --host 10.0.0.1 --top | jq -r .port
+//! night_tool --no-ui --host 192.168.1.0/24 --top --skip-dead-hosts
+//! ```
+
+use crate::config::{Config, Profile, DEFAULT_CONFIG_PATH, DEFAULT_PROFILES_PATH, DEFAULT_STATE_PATH};
+use crate::discovery::{self, PingMethod};
+use crate::export::LiveJsonl;
+use crate::ports::{parse_port_spec, PortSpecError};
+use crate::scanner::{self, BannerRead, PortSelection, Protocol, Ramp, ScanConfig, ScanEvent};
+use crate::stats::LatencySamples;
+use crate::throttle::ThrottleDetector;
+use clap::Parser;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Buffered trace lines before further attempts are dropped in verbose mode.
+pub const TRACE_CAPACITY: usize = 1024;
+
+/// A port-spec flag value such as `22,80,8000-8100`.
+#[derive(Clone, Debug)]
+pub struct PortList(pub Vec<u16>);
+
+fn port_list_arg(spec: &str) -> Result<PortList, PortSpecError> {
+    parse_port_spec(spec).map(PortList)
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "night_tool", about = "Asynchronous TCP port scanner with a terminal UI")]
+pub struct Cli {
+    /// Run without the TUI and print results to stdout
+    #[arg(long)]
+    pub no_ui: bool,
+
+    /// Target IP or domain (required with --no-ui)
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// First port of the range [default: 1]
+    #[arg(long)]
+    pub start: Option<u16>,
+
+    /// Last port of the range [default: 1000]
+    #[arg(long)]
+    pub end: Option<u16>,
+
+    /// Scan the ranked list of popular ports instead of a range
+    #[arg(long)]
+    pub top: bool,
+
+    /// Only scan the N highest-ranked popular ports (implies --top)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["start", "end"])]
+    pub top_count: Option<u64>,
+
+    /// Ranked port list replacing the built-in popular ports, most important first
+    #[arg(long, value_name = "PATH")]
+    pub top_ports_file: Option<PathBuf>,
+
+    /// Explicit ports and ranges to scan instead of --start/--end, e.g. `22,80,8000-8100`
+    #[arg(long, value_name = "SPEC", conflicts_with_all = ["top", "top_count", "start", "end"])]
+    pub ports: Option<String>,
+
+    /// Only print `host:port service` for open ports; the summary goes to stderr
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Print each result as one JSON object per line; the summary goes to stderr
+    #[arg(long)]
+    pub json: bool,
+
+    /// How headless mode prints results (`--format jsonl` is the same as `--json`)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Max random delay (ms) before each initial worker's first connect; 0 disables
+    #[arg(long)]
+    pub jitter_ms: Option<u64>,
+
+    /// Max simultaneous probes (defaults to 256 for ranges, 128 for top ports)
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+
+    /// Start at most N connection attempts per second, retries included
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_pps: Option<u32>,
+
+    /// Extra attempts for ports that stay silent, up to 5 (defaults to 1 for ranges, 0 for top ports)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(..=i64::from(scanner::MAX_RETRIES)))]
+    pub retries: Option<u8>,
+
+    /// Wait before the first retry of a silent port, in milliseconds; doubles for each further retry [default: 100]
+    #[arg(long, value_name = "MS")]
+    pub retry_backoff_ms: Option<u64>,
+
+    /// Connect TCP probes through a SOCKS5 proxy (no authentication), e.g. a jump box's `ssh -D` port
+    #[arg(long, value_name = "ADDR:PORT")]
+    pub proxy: Option<SocketAddr>,
+
+    /// Probe every port over both TCP and UDP
+    #[arg(long)]
+    pub tcp_udp: bool,
+
+    /// Log every connection attempt with its outcome and timing, including retries
+    #[arg(long, short)]
+    pub verbose: bool,
+
+    /// Peek at banners instead of consuming them, to avoid disturbing stateful protocols
+    #[arg(long)]
+    pub peek_banner: bool,
+
+    /// How long to wait for a service greeting, in milliseconds (default 500 for ranges, 1500 for top ports)
+    #[arg(long, value_name = "MS")]
+    pub banner_timeout_ms: Option<u64>,
+
+    /// Hard cap on any single banner read in milliseconds, overriding longer banner timeouts
+    #[arg(long, value_name = "MS")]
+    pub banner_cap_ms: Option<u64>,
+
+    /// Only send active probes (HTTP request, UDP service requests) to these ports, e.g. `80,443,8000-8100`
+    #[arg(long, value_name = "SPEC", value_parser = port_list_arg)]
+    pub probe_only: Option<PortList>,
+
+    /// Never send active probes to these ports; they still get a passive banner read
+    #[arg(long, value_name = "SPEC", value_parser = port_list_arg)]
+    pub no_probe: Option<PortList>,
+
+    /// Read complete greetings on FTP/SSH/SMTP/POP3/IMAP ports (e.g. multi-line SMTP 220- banners)
+    #[arg(long)]
+    pub full_greeting: bool,
+
+    /// Longest banner kept per port in bytes; longer ones are cut and marked [truncated]. 0 skips banner grabbing
+    #[arg(long, value_name = "BYTES")]
+    pub max_banner_bytes: Option<usize>,
+
+    /// Ramp concurrency up: start with this many probes and add as many again every interval
+    #[arg(long, value_name = "N")]
+    pub ramp_step: Option<usize>,
+
+    /// Interval between concurrency ramp steps, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 1000, requires = "ramp_step")]
+    pub ramp_interval_ms: u64,
+
+    /// Scan every address the host resolves to and report results per address
+    #[arg(long)]
+    pub all_addresses: bool,
+
+    /// Probe ports in random order instead of ascending
+    #[arg(long)]
+    pub shuffle: bool,
+
+    /// Seed for --shuffle so the port order is reproducible (implies --shuffle)
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+
+    /// Only report open (or ambiguous) ports; closed and timed-out ports are counted but not sent
+    #[arg(long)]
+    pub open_only: bool,
+
+    /// Race IPv4 and IPv6 connections for dual-stack hosts and keep the first to succeed
+    #[arg(long)]
+    pub happy_eyeballs: bool,
+
+    /// Ping each target address first (ICMP, or TCP 80/443 without privileges) and skip hosts that don't answer
+    #[arg(long)]
+    pub skip_dead_hosts: bool,
+
+    /// End the scan once N open ports have been found
+    #[arg(long, value_name = "N")]
+    pub stop_after_open: Option<usize>,
+
+    /// Abort the whole scan after this many seconds, keeping the results gathered so far
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub deadline: Option<u64>,
+
+    /// Also report latency over closed ports (connect-refused times), next to the open-port figures
+    #[arg(long)]
+    pub latency_closed: bool,
+
+    /// Draw the TUI inline instead of on the alternate screen (used automatically if the terminal lacks one)
+    #[arg(long)]
+    pub no_alt_screen: bool,
+
+    /// Apply at most N results per UI tick for smoother rendering on fast scans (default or 0: unlimited)
+    #[arg(long, value_name = "N")]
+    pub batch: Option<usize>,
+
+    /// Append every result to this JSON Lines file the moment it arrives (follow it with tail -f)
+    #[arg(long, value_name = "PATH")]
+    pub live_jsonl: Option<PathBuf>,
+
+    /// Config file holding target profiles
+    #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+    pub config: PathBuf,
+
+    /// File the TUI saves its view preferences to on exit
+    #[arg(long, default_value = DEFAULT_STATE_PATH)]
+    pub state: PathBuf,
+
+    /// Profiles saved from the TUI with Ctrl+S, offered alongside those in the config file
+    #[arg(long, default_value = DEFAULT_PROFILES_PATH)]
+    pub profiles: PathBuf,
+
+    /// nmap-style services file (`name port/proto` lines) used to name ports, before the built-in table
+    #[arg(long, value_name = "PATH")]
+    pub services: Option<PathBuf>,
+
+    /// Use a named target profile from the config file
+    #[arg(long)]
+    pub profile: Option<String>,
+}
+
+/// Result output of headless mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One `host:port/protocol status service time` line per result, then the summary
+    #[default]
+    Text,
+    /// One JSON object per result and nothing else on stdout
+    Jsonl,
+}
+
+impl Cli {
+    /// Whether headless mode prints JSON Lines (`--json` or `--format jsonl`).
+    pub fn json_lines(&self) -> bool {
+        self.json || self.format == OutputFormat::Jsonl
+    }
+
+    /// Looks up `--profile` in the config (saved profiles merged in). Errors if the name is unknown.
+    pub fn selected_profile(&self, config: &Config) -> Result<Option<Profile>, String> {
+        match &self.profile {
+            None => Ok(None),
+            Some(name) => config
+                .profile(name)
+                .cloned()
+                .map(Some)
+                .ok_or_else(|| format!("Unknown profile '{}' in {}", name, self.config.display())),
+        }
+    }
+
+    /// With `--verbose`, gives `config` a trace channel and returns its receiving end.
+    /// Otherwise `config` stays untraced and `None` is returned.
+    pub fn attach_trace(&self, config: &mut ScanConfig) -> Option<mpsc::Receiver<String>> {
+        if !self.verbose {
+            return None;
+        }
+        let (trace_tx, trace_rx) = mpsc::channel::<String>(TRACE_CAPACITY);
+        config.trace = Some(trace_tx);
+        Some(trace_rx)
+    }
+
+    /// Builds the scanner tunables from the parsed flags. Used by both the TUI and headless mode.
+    pub fn scan_config(&self) -> ScanConfig {
+        ScanConfig {
+            start_jitter: self.jitter_ms.filter(|&ms| ms > 0).map(Duration::from_millis),
+            concurrency: self.concurrency,
+            max_pps: self.max_pps,
+            proxy: self.proxy,
+            retries: self.retries,
+            retry_backoff: self.retry_backoff_ms.map(Duration::from_millis),
+            protocols: if self.tcp_udp { vec![Protocol::Tcp, Protocol::Udp] } else { Vec::new() },
+            banner_read: if self.peek_banner { BannerRead::Peek } else { BannerRead::Read },
+            max_banner_bytes: self.max_banner_bytes,
+            greeting_aware: self.full_greeting,
+            probe_allow: self.probe_only.clone().map(|p| p.0).unwrap_or_default(),
+            probe_deny: self.no_probe.clone().map(|p| p.0).unwrap_or_default(),
+            banner_timeout: self.banner_timeout_ms.map(Duration::from_millis),
+            banner_hard_cap: self.banner_cap_ms.map(Duration::from_millis),
+            happy_eyeballs: self.happy_eyeballs,
+            skip_dead_hosts: self.skip_dead_hosts,
+            stop_after_open: self.stop_after_open.filter(|&n| n > 0),
+            deadline: self.deadline.map(Duration::from_secs),
+            open_only: self.open_only,
+            shuffle: self.shuffle || self.seed.is_some(),
+            seed: self.seed,
+            ramp: self.ramp_step.filter(|&n| n > 0).map(|step| Ramp {
+                step,
+                interval: Duration::from_millis(self.ramp_interval_ms.max(1)),
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Formats resolved addresses for the hostname-to-IP log line.
+pub fn join_ips(ips: &[std::net::IpAddr]) -> String {
+    ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// The method host discovery will use for `host`, if `config` asks for discovery.
+/// Hostnames are assumed to resolve to IPv4.
+pub fn ping_method_for(host: &str, config: &ScanConfig) -> Option<PingMethod> {
+    if !config.skip_dead_hosts || config.proxy.is_some() {
+        return None;
+    }
+    let addr = config.addresses.first().copied().or_else(|| scanner::parse_targets(host).first().copied());
+    Some(discovery::ping_method(addr.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))))
+}
+
+/// Runs a scan without the TUI. Flags take precedence over the selected profile.
+pub async fn run_headless(cli: Cli, profile: Option<Profile>) -> Result<(), Box<dyn std::error::Error>> {
+    let host = match cli.host.as_deref().or(profile.as_ref().map(|p| p.host.as_str())).map(str::trim) {
+        Some(h) if !h.is_empty() => h.to_string(),
+        _ => return Err("--host (or --profile) is required with --no-ui".into()),
+    };
+    let start = cli.start.or(profile.as_ref().and_then(|p| p.start)).unwrap_or(1);
+    let end = cli.end.or(profile.as_ref().and_then(|p| p.end)).unwrap_or(1000);
+    let top = cli.top || cli.top_count.is_some() || profile.as_ref().is_some_and(|p| p.top);
+    let ports = cli.ports.as_deref().map(parse_port_spec).transpose().map_err(|e| format!("Invalid --ports: {}", e))?;
+
+    if !top && ports.is_none() && (start == 0 || end == 0 || start > end) {
+        return Err("Invalid port range".into());
+    }
+
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(2048);
+    let mut config = cli.scan_config();
+    if let Some(p) = &profile {
+        p.apply_to(&mut config);
+    }
+    if let Some(mut trace_rx) = cli.attach_trace(&mut config) {
+        tokio::spawn(async move {
+            while let Some(line) = trace_rx.recv().await {
+                eprintln!("{}", line);
+            }
+        });
+    }
+    if host.contains('/') && scanner::parse_targets(&host).is_empty() {
+        return Err(format!("Invalid CIDR block {} (largest allowed is a /16)", host).into());
+    }
+    if cli.all_addresses && !host.contains('/') {
+        let ips = scanner::resolve_all(&host).await.map_err(|e| format!("cannot resolve {}: {}", host, e))?;
+        eprintln!("{} -> {}", host, join_ips(&ips));
+        config.addresses = ips;
+    }
+    if let Some(proxy) = config.proxy {
+        eprintln!("Connecting through SOCKS5 proxy {}", proxy);
+    }
+    if let Some(method) = ping_method_for(&host, &config) {
+        eprintln!("Skipping hosts that don't answer a {} ping", method);
+    }
+    config.host = host.clone();
+    config.ports = match ports {
+        Some(ports) => PortSelection::List(ports),
+        None if top => match cli.top_count {
+            Some(n) => PortSelection::TopN(n as usize),
+            None => PortSelection::Top,
+        },
+        None => PortSelection::Range(start, end),
+    };
+    tokio::spawn(scanner::scan(config, tx));
+
+    let started_at = Instant::now();
+    let mut scanned = 0usize;
+    // Probes done per the last progress event, counting results that were not sent.
+    let mut done = 0usize;
+    let mut deadline_hit = None;
+    let mut open = 0usize;
+    let mut throttle = ThrottleDetector::default();
+    let mut latency = LatencySamples::default();
+    let mut live = match &cli.live_jsonl {
+        Some(path) => {
+            let live = LiveJsonl::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+            eprintln!("Streaming results to {}", path.display());
+            Some(live)
+        }
+        None => None,
+    };
+
+    while let Some(event) = rx.recv().await {
+        let result = match event {
+            ScanEvent::Result(result) => result,
+            ScanEvent::Progress { done: n, .. } => {
+                done = n;
+                continue;
+            }
+            ScanEvent::Finished { timed_out, elapsed, .. } => {
+                deadline_hit = timed_out.then_some(elapsed);
+                break;
+            }
+            ScanEvent::Error(e) => return Err(e.into()),
+        };
+        if let Some(w) = &mut live {
+            w.write(&result).map_err(|e| format!("writing live results failed: {}", e))?;
+        }
+
+        scanned += 1;
+        latency.observe(&result);
+        if let Some(warning) = throttle.observe(&result) {
+            eprintln!("{}", warning);
+        }
+        if result.status == "open" {
+            open += 1;
+        }
+
+        if cli.json_lines() {
+            if !cli.quiet || result.status == "open" {
+                println!("{}", serde_json::to_string(&result)?);
+            }
+        } else if cli.quiet {
+            if result.status == "open" {
+                match result.protocol {
+                    Protocol::Tcp => println!("{}:{} {}", result.host, result.port, result.service),
+                    Protocol::Udp => println!("{}:{}/udp {}", result.host, result.port, result.service),
+                }
+            }
+        } else {
+            let error = match &result.error {
+                Some(e) if cli.verbose || result.status == "error" => format!(" ({})", e),
+                _ => String::new(),
+            };
+            println!(
+                "{}:{}/{} {} {} {}ms{}",
+                result.host, result.port, result.protocol, result.status, result.service, result.response_ms, error
+            );
+        }
+    }
+
+    let scanned = scanned.max(done);
+    let summary = format!(
+        "Scan finished in {:.2}s: {} scanned, {} open",
+        started_at.elapsed().as_secs_f64(),
+        scanned,
+        open
+    );
+    let deadline = deadline_hit.map(|elapsed| format!("Scan hit deadline after {}s", elapsed.as_secs()));
+    let stopped = cli.stop_after_open.filter(|&n| n > 0 && open >= n).map(|n| format!("Stopped after {} open ports", n));
+    for line in deadline.into_iter().chain(std::iter::once(summary)).chain(stopped).chain(latency.summary(cli.latency_closed)) {
+        if cli.quiet || cli.json_lines() {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
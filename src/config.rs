@@ -0,0 +1,198 @@
+//! Config module for the optional `night_tool.toml` file and the TUI state file.
+//! The config file holds named target profiles that can be picked at launch
+//! (`--profile NAME`) or cycled through in the TUI. The state file
+//! (`night_tool_state.toml`) is written by the TUI on exit to remember view preferences
+//! and result notes.
+//! Profiles saved from the TUI go to their own file (`profiles.toml`) for the same reason.
+//! # Structs
+//! - `Config` - Parsed contents of the config file.
+//! - `Profile` - A named target: host, ports and optional scan settings.
+//! - `UiPrefs` - View preferences and result notes restored on the next TUI launch.
+//! - `SavedNote` - A note on one result, as kept in the state file.
+//! # Functions
+//! - `load_profiles(path: &Path)` - Reads profiles saved from the TUI.
+//! - `save_profile(path: &Path, profile: &Profile)` - Adds or replaces a saved profile by name.
+//! # Enums
+//! - `ConfigError` - Failure to read or parse the config file.
+//! # Examples
+//! ```toml
+//! operator = "jane.doe (ACME pentest #42)"
+//!
+//! [[profiles]]
+//! name = "web-servers"
+//! host = "10.0.0.5"
+//! start = 80
+//! end = 8443
+//! concurrency = 64
+//! timeout_ms = 5000
+//!
+//! [[profiles]]
+//! name = "dns"
+//! host = "10.0.0.53"
+//! top = true
+//! tcp_udp = true
+//!
+//! [[profiles]]
+//! name = "dual-stack"
+//! host = "example.com"
+//! top = true
+//! happy_eyeballs = true
+//! ```
+
+use crate::scanner::{Protocol, ScanConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+pub const DEFAULT_CONFIG_PATH: &str = "night_tool.toml";
+
+pub const DEFAULT_STATE_PATH: &str = "night_tool_state.toml";
+
+pub const DEFAULT_PROFILES_PATH: &str = "profiles.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("invalid config {path}: {source}")]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("cannot write {path}: {source}")]
+    Write { path: PathBuf, source: std::io::Error },
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+    /// Name recorded as the operator in audit logs.
+    pub operator: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+/// A named scan target. Unset fields fall back to command-line flags or defaults.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub host: String,
+    pub start: Option<u16>,
+    pub end: Option<u16>,
+    /// Scan the popular-ports list instead of a range.
+    #[serde(default)]
+    pub top: bool,
+    pub concurrency: Option<usize>,
+    /// Connect timeout in milliseconds.
+    pub timeout_ms: Option<u64>,
+    pub jitter_ms: Option<u64>,
+    pub tcp_udp: Option<bool>,
+    pub happy_eyeballs: Option<bool>,
+}
+
+/// TUI view preferences and result notes. Kept apart from `Config` so saving them
+/// never rewrites the hand-edited config file. Unknown or missing keys fall back to defaults.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct UiPrefs {
+    pub compact: bool,
+    pub group_by_service: bool,
+    pub coalesce_log: bool,
+    pub show_only_open: bool,
+    pub notes: Vec<SavedNote>,
+}
+
+/// A note attached to the result for `host`, `port` and `protocol`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SavedNote {
+    pub host: String,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub text: String,
+}
+
+impl Default for UiPrefs {
+    fn default() -> Self {
+        Self { compact: false, group_by_service: false, coalesce_log: true, show_only_open: false, notes: Vec::new() }
+    }
+}
+
+impl UiPrefs {
+    /// Loads saved preferences. A missing file yields the defaults.
+    pub fn load(path: &Path) -> Result<UiPrefs, ConfigError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(UiPrefs::default()),
+            Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+        };
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let text = toml::to_string(self).expect("UiPrefs serializes to TOML");
+        std::fs::write(path, text).map_err(|source| ConfigError::Write { path: path.to_path_buf(), source })
+    }
+}
+
+impl Config {
+    /// Loads the config file. A missing file yields an empty config.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+        };
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+}
+
+impl Profile {
+    /// Fills scan settings the caller left unset. Values already set (e.g. from flags) win.
+    pub fn apply_to(&self, config: &mut ScanConfig) {
+        if config.concurrency.is_none() {
+            config.concurrency = self.concurrency;
+        }
+        if config.timeout.is_none() {
+            config.timeout = self.timeout_ms.map(Duration::from_millis);
+        }
+        if config.start_jitter.is_none() {
+            config.start_jitter = self.jitter_ms.filter(|&ms| ms > 0).map(Duration::from_millis);
+        }
+        if config.protocols.is_empty() && self.tcp_udp == Some(true) {
+            config.protocols = vec![Protocol::Tcp, Protocol::Udp];
+        }
+        if self.happy_eyeballs == Some(true) {
+            config.happy_eyeballs = true;
+        }
+    }
+}
+
+/// On-disk layout of the saved profiles file.
+#[derive(Default, Deserialize, Serialize)]
+struct SavedProfiles {
+    #[serde(default)]
+    profiles: Vec<Profile>,
+}
+
+/// Reads profiles saved from the TUI. A missing file yields none.
+pub fn load_profiles(path: &Path) -> Result<Vec<Profile>, ConfigError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+    };
+    let saved: SavedProfiles = toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+    Ok(saved.profiles)
+}
+
+/// Writes `profile` to the saved profiles file, replacing any saved profile with the same name.
+pub fn save_profile(path: &Path, profile: &Profile) -> Result<(), ConfigError> {
+    let mut profiles = load_profiles(path)?;
+    match profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile.clone(),
+        None => profiles.push(profile.clone()),
+    }
+    let text = toml::to_string(&SavedProfiles { profiles }).expect("profiles serialize to TOML");
+    std::fs::write(path, text).map_err(|source| ConfigError::Write { path: path.to_path_buf(), source })
+}
@@ -0,0 +1,180 @@
+//! Connector module defining how the scanner opens TCP connections.
+//! The scan core never calls `TcpStream::connect` directly; it goes through a `Connector`,
+//! so library users can route connections through an existing tunnel or multiplexer.
+//! # Traits
+//! - `Connector` - Opens a stream to `host:port` and returns it boxed.
+//! - `ScanStream` - Any async byte stream the scanner can read banners from, optionally by peeking.
+//! # Structs
+//! - `DirectConnector` - Default connector using `tokio::net::TcpStream`.
+//! - `Socks5Connector` - Connects through a SOCKS5 proxy (no authentication), e.g. `ssh -D`.
+//! # Examples
+//! ```no_run
+//! use night_tool::connector::{BoxedStream, Connector, ConnectFuture};
+//! use night_tool::scanner::ScanConfig;
+//! use std::sync::Arc;
+//!
+//! #[derive(Debug)]
+//! struct Tunnel;
+//!
+//! impl Connector for Tunnel {
+//!     fn connect<'a>(&'a self, host: &'a str, port: u16) -> ConnectFuture<'a> {
+//!         Box::pin(async move {
+//!             // open a channel through the tunnel here
+//!             let stream = tokio::net::TcpStream::connect((host, port)).await?;
+//!             Ok(Box::new(stream) as BoxedStream)
+//!         })
+//!     }
+//! }
+//!
+//! let config = ScanConfig { connector: Some(Arc::new(Tunnel)), ..Default::default() };
+//! ```
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// A connected byte stream the scanner can read from and write probes to.
+/// Custom stream types only need `impl ScanStream for MyStream {}`.
+pub trait ScanStream: AsyncRead + AsyncWrite + Unpin + Send {
+    /// Reads pending bytes without consuming them, if the transport supports it.
+    /// Returns `None` so the scanner falls back to a regular read.
+    fn peek<'a>(&'a mut self, _buf: &'a mut [u8]) -> Option<PeekFuture<'a>> {
+        None
+    }
+}
+
+impl ScanStream for TcpStream {
+    fn peek<'a>(&'a mut self, buf: &'a mut [u8]) -> Option<PeekFuture<'a>> {
+        Some(Box::pin(TcpStream::peek(self, buf)))
+    }
+}
+
+pub type PeekFuture<'a> = Pin<Box<dyn Future<Output = io::Result<usize>> + Send + 'a>>;
+
+pub type BoxedStream = Box<dyn ScanStream>;
+
+pub type ConnectFuture<'a> = Pin<Box<dyn Future<Output = io::Result<BoxedStream>> + Send + 'a>>;
+
+/// Opens the connection used to probe a single port.
+pub trait Connector: Send + Sync + Debug {
+    fn connect<'a>(&'a self, host: &'a str, port: u16) -> ConnectFuture<'a>;
+}
+
+/// Connects straight to the target with `TcpStream::connect`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectConnector;
+
+impl Connector for DirectConnector {
+    fn connect<'a>(&'a self, host: &'a str, port: u16) -> ConnectFuture<'a> {
+        Box::pin(async move {
+            let stream = TcpStream::connect((host, port)).await?;
+            let _ = stream.set_nodelay(true);
+            Ok(Box::new(stream) as BoxedStream)
+        })
+    }
+}
+
+/// Connects through a SOCKS5 proxy. Hostnames are sent to the proxy unresolved, so
+/// names only the far side can resolve still work. The proxy's reply codes become the
+/// matching `io::ErrorKind`s, so a port the target refuses is still reported "closed".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Socks5Connector {
+    pub proxy: SocketAddr,
+}
+
+impl Socks5Connector {
+    pub fn new(proxy: SocketAddr) -> Self {
+        Self { proxy }
+    }
+}
+
+impl Connector for Socks5Connector {
+    fn connect<'a>(&'a self, host: &'a str, port: u16) -> ConnectFuture<'a> {
+        Box::pin(async move {
+            let mut stream = TcpStream::connect(self.proxy).await?;
+            let _ = stream.set_nodelay(true);
+            socks5_handshake(&mut stream, host, port).await?;
+            Ok(Box::new(stream) as BoxedStream)
+        })
+    }
+}
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_NO_AUTH: u8 = 0;
+const SOCKS_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+/// Runs the RFC 1928 greeting and CONNECT request on `stream`, leaving it relaying to
+/// `host:port`.
+async fn socks5_handshake(stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+    let bad_reply = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("SOCKS5 proxy sent {}", what));
+    stream.write_all(&[SOCKS_VERSION, 1, SOCKS_NO_AUTH]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != SOCKS_VERSION {
+        return Err(bad_reply("an invalid greeting"));
+    }
+    if choice[1] != SOCKS_NO_AUTH {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "SOCKS5 proxy requires authentication"));
+    }
+
+    let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let name = u8::try_from(host.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "hostname too long for SOCKS5"))?;
+            request.push(ATYP_DOMAIN);
+            request.push(name);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(bad_reply("an invalid reply"));
+    }
+    if reply[1] != 0 {
+        return Err(socks5_error(reply[1]));
+    }
+    // Skip the bound address; the stream carries the target's bytes from here on.
+    let bound = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => usize::from(stream.read_u8().await?),
+        _ => return Err(bad_reply("an unknown address type")),
+    };
+    let mut skip = vec![0u8; bound + 2];
+    stream.read_exact(&mut skip).await?;
+    Ok(())
+}
+
+/// Maps a SOCKS5 reply code to the error a direct connect would have produced.
+fn socks5_error(code: u8) -> io::Error {
+    let (kind, msg) = match code {
+        2 => (io::ErrorKind::PermissionDenied, "connection not allowed by ruleset"),
+        3 => (io::ErrorKind::NetworkUnreachable, "network unreachable"),
+        4 => (io::ErrorKind::HostUnreachable, "host unreachable"),
+        5 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        6 => (io::ErrorKind::TimedOut, "TTL expired"),
+        7 | 8 => (io::ErrorKind::Unsupported, "command or address type not supported"),
+        _ => (io::ErrorKind::Other, "general failure"),
+    };
+    io::Error::new(kind, format!("SOCKS5 proxy: {}", msg))
+}
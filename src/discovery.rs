@@ -0,0 +1,170 @@
+//! Discovery module for finding live hosts before a port sweep.
+//! Hosts are pinged with an ICMP echo request. Opening an ICMP socket needs either
+//! root (a raw socket) or membership of `net.ipv4.ping_group_range` on Linux (a ping
+//! socket); without either, hosts are "pinged" with TCP connects to ports 80 and 443,
+//! where a refused connection counts as alive just like an accepted one.
+//! # Enums
+//! - `PingMethod` - How hosts are pinged: ICMP echo or TCP connect.
+//! # Functions
+//! - `ping_method(addr: IpAddr) -> PingMethod` - The method `ping_host` will use for an address family.
+//! - `ping_host(addr: IpAddr, timeout: Duration) -> bool` - Whether a host answers a ping within `timeout`.
+//! - `ping_with(method: PingMethod, addr: IpAddr, timeout: Duration) -> bool` - Pings with a chosen method.
+//! # Examples
+//! ```no_run
+//! # async fn run() {
+//! use night_tool::discovery::{ping_host, ping_method};
+//! use std::time::Duration;
+//!
+//! let addr = "192.168.1.10".parse().unwrap();
+//! println!("pinging via {}", ping_method(addr));
+//! if ping_host(addr, Duration::from_secs(1)).await {
+//!     println!("{} is up", addr);
+//! }
+//! # }
+//! ```
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::task::JoinSet;
+
+/// Ports tried by the TCP fallback.
+pub const TCP_PING_PORTS: &[u16] = &[80, 443];
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PingMethod {
+    Icmp,
+    /// Connects to `TCP_PING_PORTS`.
+    Tcp,
+}
+
+impl fmt::Display for PingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PingMethod::Icmp => write!(f, "ICMP"),
+            PingMethod::Tcp => write!(f, "TCP 80/443"),
+        }
+    }
+}
+
+/// An ICMP socket for `addr`'s family, and whether it is raw (replies then carry the
+/// IPv4 header and every ICMP packet the host receives, not just ours).
+fn icmp_socket(addr: IpAddr) -> io::Result<(Socket, bool)> {
+    let (domain, protocol) = match addr {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+    };
+    match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+        Ok(socket) => Ok((socket, false)),
+        Err(_) => Socket::new(domain, Type::RAW, Some(protocol)).map(|socket| (socket, true)),
+    }
+}
+
+/// ICMP when an ICMP socket can be opened for `addr`'s family, TCP otherwise.
+pub fn ping_method(addr: IpAddr) -> PingMethod {
+    match icmp_socket(addr) {
+        Ok(_) => PingMethod::Icmp,
+        Err(_) => PingMethod::Tcp,
+    }
+}
+
+/// Pings `addr` with `ping_method(addr)`.
+pub async fn ping_host(addr: IpAddr, timeout: Duration) -> bool {
+    ping_with(ping_method(addr), addr, timeout).await
+}
+
+/// Pings `addr` with `method`. An ICMP ping that cannot be sent falls back to TCP.
+pub async fn ping_with(method: PingMethod, addr: IpAddr, timeout: Duration) -> bool {
+    match method {
+        PingMethod::Icmp => match icmp_ping(addr, timeout).await {
+            Ok(alive) => alive,
+            Err(_) => tcp_ping(addr, timeout).await,
+        },
+        PingMethod::Tcp => tcp_ping(addr, timeout).await,
+    }
+}
+
+async fn icmp_ping(addr: IpAddr, timeout: Duration) -> io::Result<bool> {
+    let (socket, raw) = icmp_socket(addr)?;
+    socket.set_nonblocking(true)?;
+    let socket = UdpSocket::from_std(std::net::UdpSocket::from(socket))?;
+    let ident: u16 = rand::random();
+    socket.send_to(&echo_request(addr, ident), SocketAddr::new(addr, 0)).await?;
+
+    let reply = async {
+        let mut buf = [0u8; 1500];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).await?;
+            if from.ip() == addr && is_echo_reply(&buf[..n], addr, raw.then_some(ident)) {
+                return Ok(true);
+            }
+        }
+    };
+    tokio::time::timeout(timeout, reply).await.unwrap_or(Ok(false))
+}
+
+fn echo_request(addr: IpAddr, ident: u16) -> Vec<u8> {
+    let kind = if addr.is_ipv6() { ICMPV6_ECHO_REQUEST } else { ICMPV4_ECHO_REQUEST };
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    // Sequence number 1, then a short payload.
+    packet.extend_from_slice(&[0, 1]);
+    packet.extend_from_slice(b"night_tool");
+    // The kernel fills in ICMPv6 checksums (they cover a pseudo-header we don't see).
+    if addr.is_ipv4() {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// Internet checksum (RFC 1071).
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2).map(|c| u32::from(u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))).sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Whether `packet` is an echo reply. Ping sockets rewrite the identifier and only
+/// deliver our own replies, so it is checked (`ident`) on raw sockets only.
+fn is_echo_reply(packet: &[u8], addr: IpAddr, ident: Option<u16>) -> bool {
+    let icmp = match addr {
+        // Raw IPv4 sockets deliver the IP header too.
+        IpAddr::V4(_) if ident.is_some() => packet.get(usize::from(packet.first().unwrap_or(&0) & 0x0f) * 4..),
+        _ => Some(packet),
+    };
+    let Some(icmp) = icmp.filter(|p| p.len() >= 8) else { return false };
+    let reply = if addr.is_ipv6() { ICMPV6_ECHO_REPLY } else { ICMPV4_ECHO_REPLY };
+    icmp[0] == reply && ident.is_none_or(|id| icmp[4..6] == id.to_be_bytes())
+}
+
+/// Alive if any of `TCP_PING_PORTS` accepts or refuses the connection; only silence
+/// (or an unreachable error) counts as down.
+async fn tcp_ping(addr: IpAddr, timeout: Duration) -> bool {
+    let mut attempts = JoinSet::new();
+    for &port in TCP_PING_PORTS {
+        attempts.spawn(async move {
+            match tokio::time::timeout(timeout, TcpStream::connect((addr, port))).await {
+                Ok(Ok(_)) => true,
+                Ok(Err(e)) => e.kind() == io::ErrorKind::ConnectionRefused,
+                Err(_) => false,
+            }
+        });
+    }
+    while let Some(alive) = attempts.join_next().await {
+        if alive.unwrap_or(false) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,391 @@
+//! Export module for writing scan results to files or other sinks such as the clipboard.
+//! # Structs
+//! - `LiveJsonl` - Appends results to a JSON Lines file as they arrive, so they survive a crash.
+//! # Types
+//! - `Notes` - Notes on results by host, port and protocol, written alongside them.
+//! # Functions
+//! - `export_nmap_xml(results: &[ScanResult], host: &str, started: SystemTime, finished: SystemTime, path: &Path)` - Writes a minimal nmap-compatible XML report.
+//! - `export_json(results: &[ScanResult], notes: &Notes, path: &Path)` - Writes results and their notes as a JSON array.
+//! - `export_csv(results: &[ScanResult], notes: &Notes, path: &Path)` - Writes results and their notes as CSV for spreadsheets.
+//! - `write_tsv<W: Write>(results: &[ScanResult], notes: &Notes, w: W)` - Writes results and their notes as tab-separated values with a header row.
+//! - `write_report<W: Write>(app: &App, w: W)` - Writes a human-readable plain-text report.
+//! - `write_audit_log<W: Write>(app: &App, w: W)` - Writes a checksum-chained record of every scan job and log event.
+//! - `timestamp_slug(t: SystemTime) -> String` - Formats a UTC time as `YYYYMMDD_HHMMSS` for file names.
+//! - `format_utc(t: SystemTime) -> String` - Formats a UTC time as `YYYY-MM-DD HH:MM:SS UTC`.
+//! # Examples
+//! ```no_run
+//! use night_tool::export::{export_nmap_xml, timestamp_slug};
+//! use std::path::Path;
+//! use std::time::SystemTime;
+//!
+//! let now = SystemTime::now();
+//! let name = format!("scan_{}.xml", timestamp_slug(now));
+//! export_nmap_xml(&[], "10.0.0.1", now, now, Path::new(&name)).unwrap();
+//! ```
+
+use crate::scanner::{Protocol, ScanResult};
+use crate::ui::App;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Notes on results, keyed by (result host, port, protocol) as in `App::notes`.
+pub type Notes = HashMap<(String, u16, Protocol), String>;
+
+fn note_text<'a>(notes: &'a Notes, r: &ScanResult) -> Option<&'a str> {
+    notes.get(&(r.host.clone(), r.port, r.protocol)).map(String::as_str)
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Splits a time into UTC (year, month, day, hour, minute, second).
+fn utc_parts(t: SystemTime) -> (i64, i64, i64, u64, u64, u64) {
+    let secs = unix_secs(t);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Formats a UTC time as `YYYYMMDD_HHMMSS`.
+pub fn timestamp_slug(t: SystemTime) -> String {
+    let (y, mo, d, h, mi, s) = utc_parts(t);
+    format!("{:04}{:02}{:02}_{:02}{:02}{:02}", y, mo, d, h, mi, s)
+}
+
+/// Formats a UTC time as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_utc(t: SystemTime) -> String {
+    let (y, mo, d, h, mi, s) = utc_parts(t);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", y, mo, d, h, mi, s)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// A result as exported to JSON, with its note (if any) alongside the scan fields.
+#[derive(Serialize)]
+struct NotedResult<'a> {
+    #[serde(flatten)]
+    result: &'a ScanResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+}
+
+/// Writes every result as one object of a pretty-printed JSON array. Annotated
+/// results carry a `note` field.
+pub fn export_json(results: &[ScanResult], notes: &Notes, path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    let rows: Vec<NotedResult> = results.iter().map(|r| NotedResult { result: r, note: note_text(notes, r) }).collect();
+    serde_json::to_writer_pretty(&mut w, &rows)?;
+    writeln!(w)?;
+    w.flush()
+}
+
+/// A JSON Lines file results are appended to as they arrive. Each line is flushed
+/// as soon as it is written, so the file can be followed with `tail -f` and keeps
+/// everything received if the process dies. Closed when dropped.
+#[derive(Debug)]
+pub struct LiveJsonl {
+    out: BufWriter<File>,
+}
+
+impl LiveJsonl {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { out: BufWriter::new(file) })
+    }
+
+    /// Appends `result` as one line.
+    pub fn write(&mut self, result: &ScanResult) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, result)?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+/// Line breaks are flattened to spaces so each record stays on one line; fields
+/// with commas or quotes are quoted, doubling inner quotes.
+fn csv_field(s: &str) -> String {
+    let flat = s.split(['\r', '\n']).filter(|p| !p.is_empty()).collect::<Vec<_>>().join(" ");
+    if flat.contains([',', '"']) {
+        format!("\"{}\"", flat.replace('"', "\"\""))
+    } else {
+        flat
+    }
+}
+
+/// Writes a `host,port,protocol,status,service,product,version,response_ms,banner,note`
+/// header and one line per result.
+pub fn export_csv(results: &[ScanResult], notes: &Notes, path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "host,port,protocol,status,service,product,version,response_ms,banner,note")?;
+    for r in results {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&r.host),
+            r.port,
+            r.protocol,
+            csv_field(&r.status),
+            csv_field(&r.service),
+            csv_field(r.software.as_ref().map_or("", |s| s.product.as_str())),
+            csv_field(r.software.as_ref().map_or("", |s| s.version.as_str())),
+            r.response_ms,
+            csv_field(r.banner.as_deref().unwrap_or("")),
+            csv_field(note_text(notes, r).unwrap_or(""))
+        )?;
+    }
+    w.flush()
+}
+
+/// Tabs and line breaks would split a TSV field, so banners are flattened to spaces.
+fn tsv_field(s: &str) -> String {
+    s.split(['\t', '\r', '\n']).filter(|p| !p.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Writes a header row followed by one tab-separated line per result.
+pub fn write_tsv<W: Write>(results: &[ScanResult], notes: &Notes, mut w: W) -> io::Result<()> {
+    writeln!(w, "host\tport\tprotocol\tstatus\tservice\tproduct\tversion\tresponse_ms\tbanner\tnote")?;
+    for r in results {
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            tsv_field(&r.host),
+            r.port,
+            r.protocol,
+            tsv_field(&r.status),
+            tsv_field(&r.service),
+            tsv_field(r.software.as_ref().map_or("", |s| s.product.as_str())),
+            tsv_field(r.software.as_ref().map_or("", |s| s.version.as_str())),
+            r.response_ms,
+            tsv_field(r.banner.as_deref().unwrap_or("")),
+            tsv_field(note_text(notes, r).unwrap_or(""))
+        )?;
+    }
+    Ok(())
+}
+
+/// Maps our result states onto nmap's port states. Nothing is passed through: a
+/// probe that errored, or any state not listed here, is reported as "filtered"
+/// since no answer was seen.
+fn nmap_state(status: &str) -> &'static str {
+    match status {
+        "open" => "open",
+        "closed" | "reset" => "closed",
+        "open|filtered" => "open|filtered",
+        "filtered" | "unreachable" | "error" => "filtered",
+        _ => "filtered",
+    }
+}
+
+/// Writes the results as a minimal subset of nmap's XML output: one `host` per scanned
+/// machine with its `ports`, each carrying `state` and `service`. The root element records the scan
+/// start and end as Unix timestamps.
+pub fn export_nmap_xml(
+    results: &[ScanResult],
+    host: &str,
+    started: SystemTime,
+    finished: SystemTime,
+    path: &Path,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    let (start, end) = (unix_secs(started), unix_secs(finished));
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<!DOCTYPE nmaprun>"#)?;
+    writeln!(
+        w,
+        r#"<nmaprun scanner="night_tool" version="{}" xmloutputversion="1.05" start="{}" end="{}">"#,
+        env!("CARGO_PKG_VERSION"),
+        start,
+        end
+    )?;
+    // One <host> per scanned machine, in order of first result; CIDR scans cover many.
+    let mut hosts: Vec<(&str, Vec<&ScanResult>)> = Vec::new();
+    for r in results {
+        let h = if r.host.is_empty() { host } else { r.host.as_str() };
+        match hosts.iter_mut().find(|(name, _)| *name == h) {
+            Some((_, rs)) => rs.push(r),
+            None => hosts.push((h, vec![r])),
+        }
+    }
+    if hosts.is_empty() {
+        hosts.push((host, Vec::new()));
+    }
+
+    for (h, rs) in &hosts {
+        writeln!(w, r#"<host starttime="{}" endtime="{}">"#, start, end)?;
+        writeln!(w, r#"<status state="up" reason="user-set"/>"#)?;
+        match h.parse::<IpAddr>() {
+            Ok(ip) => {
+                let kind = if ip.is_ipv4() { "ipv4" } else { "ipv6" };
+                writeln!(w, r#"<address addr="{}" addrtype="{}"/>"#, ip, kind)?;
+            }
+            Err(_) => {
+                writeln!(w, r#"<hostnames><hostname name="{}" type="user"/></hostnames>"#, xml_escape(h))?;
+            }
+        }
+
+        writeln!(w, "<ports>")?;
+        for r in rs {
+            writeln!(
+                w,
+                r#"<port protocol="{}" portid="{}"><state state="{}"/><service name="{}"/></port>"#,
+                r.protocol,
+                r.port,
+                xml_escape(nmap_state(&r.status)),
+                xml_escape(&r.service)
+            )?;
+        }
+        writeln!(w, "</ports>")?;
+        writeln!(w, "</host>")?;
+    }
+
+    writeln!(w, "<runstats>")?;
+    writeln!(w, r#"<finished time="{}" elapsed="{}"/>"#, end, end.saturating_sub(start))?;
+    writeln!(w, r#"<hosts up="{}" down="0" total="{}"/>"#, hosts.len(), hosts.len())?;
+    writeln!(w, "</runstats>")?;
+    writeln!(w, "</nmaprun>")?;
+    w.flush()
+}
+
+/// Writes a plain-text report for non-technical readers: a header with target, time
+/// and duration, a section per scanned host listing its open ports with services,
+/// banners and notes, and a closing summary of result states.
+pub fn write_report<W: Write>(app: &App, mut w: W) -> io::Result<()> {
+    let rule = "=".repeat(72);
+    let started = app.scan_started_wall;
+    let finished = app.scan_finished_wall;
+
+    writeln!(w, "{}", rule)?;
+    writeln!(w, "Night Tool scan report")?;
+    writeln!(w, "{}", rule)?;
+    writeln!(w, "Target:   {}", app.scan_host)?;
+    writeln!(w, "Started:  {}", started.map(format_utc).unwrap_or_else(|| "-".to_string()))?;
+    writeln!(w, "Finished: {}", finished.map(format_utc).unwrap_or_else(|| "not finished".to_string()))?;
+    if let (Some(s), Some(f)) = (started, finished) {
+        let secs = f.duration_since(s).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+        writeln!(w, "Duration: {:.1}s", secs)?;
+    }
+    writeln!(w)?;
+
+    let mut open: Vec<&ScanResult> = app.results.iter().filter(|r| r.status == "open").collect();
+    open.sort_by_key(|r| (r.port, r.protocol.to_string()));
+
+    // One section per machine; a CIDR or all-addresses scan reports several.
+    let mut hosts: Vec<String> = if app.multi_target() {
+        app.results.iter().map(|r| r.host.clone()).collect()
+    } else {
+        vec![app.scan_host.clone()]
+    };
+    hosts.sort();
+    hosts.dedup();
+
+    for host in &hosts {
+        let host_open: Vec<&&ScanResult> = open.iter().filter(|r| !app.multi_target() || &r.host == host).collect();
+        writeln!(w, "Host: {}", host)?;
+        writeln!(w, "{}", "-".repeat(72))?;
+        if host_open.is_empty() {
+            writeln!(w, "No open ports found.")?;
+        } else {
+            writeln!(w, "{:<12} {:<16} BANNER", "PORT", "SERVICE")?;
+            for r in &host_open {
+                let banner = r.banner.as_deref().map(tsv_field).unwrap_or_default();
+                writeln!(w, "{:<12} {:<16} {}", format!("{}/{}", r.port, r.protocol), r.service, banner)?;
+                if let Some(note) = app.note_for(r) {
+                    writeln!(w, "{:<12} note: {}", "", note)?;
+                }
+            }
+        }
+        writeln!(w)?;
+    }
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for r in &app.results {
+        match counts.iter_mut().find(|(s, _)| *s == r.status) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((r.status.as_str(), 1)),
+        }
+    }
+    writeln!(w, "Summary")?;
+    writeln!(w, "{}", "-".repeat(72))?;
+    writeln!(w, "Ports checked: {}", app.results.len())?;
+    for (status, n) in counts {
+        writeln!(w, "{:<14} {}", format!("{}:", status), n)?;
+    }
+    writeln!(w, "{}", rule)?;
+    Ok(())
+}
+
+/// 64-bit FNV-1a over the previous checksum and the line, chaining each audit line
+/// to everything before it.
+fn chain_checksum(prev: u64, line: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in prev.to_be_bytes().iter().chain(line.as_bytes()) {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Writes the session's scan scope as an audit record separate from the results:
+/// operator, every scan job with its target, scope and start/end times, then every
+/// log event with its timestamp. Each line starts with a checksum chained over all
+/// previous lines (64-bit FNV-1a, starting from 0), so edited, dropped or reordered
+/// lines are detectable by recomputing the chain. It is not a cryptographic signature.
+pub fn write_audit_log<W: Write>(app: &App, mut w: W) -> io::Result<()> {
+    let operator = app.operator.clone()
+        .or_else(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut lines = vec![
+        format!("night_tool {} audit log", env!("CARGO_PKG_VERSION")),
+        format!("generated {}", format_utc(SystemTime::now())),
+        format!("operator {}", operator),
+    ];
+    for (i, job) in app.scan_jobs.iter().enumerate() {
+        lines.push(format!(
+            "job {} target={} scope=\"{}\" started=\"{}\" finished=\"{}\" outcome={} scanned={} open={}",
+            i + 1,
+            job.host,
+            job.scope,
+            format_utc(job.started),
+            job.finished.map(format_utc).unwrap_or_else(|| "-".to_string()),
+            job.outcome.as_deref().unwrap_or("running"),
+            job.scanned,
+            job.open
+        ));
+    }
+    for (t, event) in app.log_entries() {
+        lines.push(format!("event \"{}\" {}", format_utc(t), tsv_field(event)));
+    }
+
+    let mut chain = 0;
+    for line in &lines {
+        chain = chain_checksum(chain, line);
+        writeln!(w, "{:016x} {}", chain, line)?;
+    }
+    w.flush()
+}
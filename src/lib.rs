@@ -0,0 +1,30 @@
+//! Night Tool library: the scanning engine, service identification and terminal UI,
+//! usable on their own or through the `night_tool` binary.
+//! # Modules
+//! - `cli` - Command-line arguments and headless mode.
+//! - `config` - Optional TOML config file with target profiles.
+//! - `connector` - Pluggable connection setup used by the scan core.
+//! - `discovery` - Ping sweeps that skip dead hosts before port scanning.
+//! - `export` - Writing results out: JSON, CSV, TSV (clipboard), live JSON Lines, nmap-compatible XML, plain-text reports and audit logs.
+//! - `ports` - Parsing of comma-separated port lists and ranges, and the top-ports ranking.
+//! - `scanner` - Asynchronous port scanning engine.
+//! - `services` - Service identification by port number, optionally from an nmap-style services file.
+//! - `stats` - Latency percentiles for open and closed ports.
+//! - `throttle` - Detection of targets rate-limiting the scanner.
+//! - `tls` - Certificate summaries for HTTPS ports.
+//! - `tui` - Backend-agnostic event loop for the interactive UI.
+//! - `ui` - Ratatui-based UI state and rendering.
+
+pub mod cli;
+pub mod config;
+pub mod connector;
+pub mod discovery;
+pub mod export;
+pub mod ports;
+pub mod scanner;
+pub mod services;
+pub mod stats;
+pub mod throttle;
+pub mod tls;
+pub mod tui;
+pub mod ui;
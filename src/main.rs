@@ -1,179 +1,175 @@
-/// Main entry point for the Night Tool application. 
-/// This application provides a terminal-based UI for scanning network ports on a specified host.
-/// It utilizes asynchronous programming with Tokio for efficient scanning and
-/// the Ratatui library for rendering the UI.
-
-mod scanner;
-mod services;
-mod ui;
-
-use scanner::ScanResult;
+//! Main entry point for the Night Tool application. 
+//! This application provides a terminal-based UI for scanning network ports on a specified host.
+//! It utilizes asynchronous programming with Tokio for efficient scanning and
+//! the Ratatui library for rendering the UI.
+
+use clap::Parser;
+use night_tool::config::{self, Config, UiPrefs};
+use night_tool::{cli, ports, services, tui, ui};
+use night_tool::scanner::ScanEvent;
 use tokio::sync::mpsc;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::backend::CrosstermBackend;
-use ratatui::Terminal;
+use ratatui::{Terminal, TerminalOptions, Viewport};
 use std::io;
-use std::time::Instant;
+
+/// Undoes raw mode, mouse capture and the alternate screen. Safe to call more than once.
+fn restore_terminal(alt_screen: bool) {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    if alt_screen {
+        let _ = execute!(stdout, LeaveAlternateScreen);
+    }
+    let _ = execute!(stdout, DisableMouseCapture, Show);
+}
+
+/// Restores the terminal when dropped, so every exit path (errors, Ctrl-C, panics)
+/// leaves a usable shell behind.
+struct TerminalGuard {
+    alt_screen: bool,
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal(self.alt_screen);
+    }
+}
 
 #[tokio::main]
 
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = cli::Cli::parse();
+    let mut config = Config::load(&cli.config)?;
+    // Profiles in the hand-edited config win over saved ones with the same name.
+    for saved in config::load_profiles(&cli.profiles)? {
+        if config.profile(&saved.name).is_none() {
+            config.profiles.push(saved);
+        }
+    }
+    let profile = cli.selected_profile(&config)?;
+    let mut services_loaded = None;
+    if let Some(path) = &cli.services {
+        let map = services::load_services(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        services_loaded = Some(map.len());
+        services::set_services(map);
+    }
+    let mut top_ports_loaded = None;
+    if let Some(path) = &cli.top_ports_file {
+        let ranked = ports::load_top_ports(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        top_ports_loaded = Some(ranked.len());
+        ports::set_top_ports(ranked);
+    }
+    if cli.no_ui {
+        return cli::run_headless(cli, profile).await;
+    }
+    let mut scan_config = cli.scan_config();
+    let trace_rx = cli.attach_trace(&mut scan_config);
+
     enable_raw_mode()?;
+    let mut guard = TerminalGuard { alt_screen: false };
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    let mut alt_screen_error = None;
+    let alt_screen = !cli.no_alt_screen && match execute!(stdout, EnterAlternateScreen) {
+        Ok(()) => true,
+        Err(e) => {
+            alt_screen_error = Some(e);
+            false
+        }
+    };
+    guard.alt_screen = alt_screen;
+    execute!(stdout, EnableMouseCapture)?;
+    // Restore before the default hook prints, or the message is lost on the alternate screen.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(alt_screen);
+        default_hook(info);
+    }));
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = if alt_screen {
+        Terminal::new(backend)?
+    } else {
+        // Render inline below the prompt, using the whole current terminal height.
+        let (_, rows) = crossterm::terminal::size()?;
+        Terminal::with_options(backend, TerminalOptions { viewport: Viewport::Inline(rows) })?
+    };
     terminal.clear()?;
 
-    let (tx, rx) = mpsc::channel::<ScanResult>(2048);
+    let (tx, rx) = mpsc::channel::<ScanEvent>(2048);
     let mut app = ui::App::new(rx);
-
-    let tick_rate = std::time::Duration::from_millis(80);
-    let mut last_tick = Instant::now();
-    let mut scan_task: Option<tokio::task::JoinHandle<()>> = None;
-    let mut scan_started_at: Option<Instant> = None;
-
-    loop {
-        terminal.draw(|f| ui::draw(f, &app))?;
-
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| std::time::Duration::from_secs(0));
-
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    continue;
-                }
-
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        if let Some(handle) = scan_task.take() {
-                            handle.abort();
-                        }
-                        break;
-                    }
-
-                    KeyCode::Char('s') | KeyCode::Enter => {
-                        let target_host = app.host_input.trim().to_string();
-                        let start_port = app.start_port_input.parse::<u16>().unwrap_or(1);
-                        let end_port = app.end_port_input.parse::<u16>().unwrap_or(65535);
-
-                        if target_host.is_empty() {
-                            app.log_events.push("Host is empty. Enter IP or domain.".to_string());
-                            continue;
-                        }
-
-                        if scan_task.is_some() {
-                            app.log_events.push("Scan already running".to_string());
-                            continue;
-                        }
-
-                        if start_port == 0 || end_port == 0 || start_port > end_port {
-                            app.log_events.push("Invalid port range".to_string());
-                            continue;
-                        }
-
-                        app.results.clear();
-                        app.total_scanned = 0;
-                        let tx_clone = tx.clone();
-                        let host_for_task = target_host.clone();
-
-                        let handle = tokio::spawn(async move {
-                            scanner::scan_range(&host_for_task, start_port, end_port, tx_clone).await;
-                        });
-
-                        scan_task = Some(handle);
-                        scan_started_at = Some(Instant::now());
-                        app.is_scanning = true;
-                        app.log_events.push(format!("Scan started: {}:{}-{}", target_host, start_port, end_port));
-                    }
-
-                    KeyCode::Char('t') => {
-                        let target_host = app.host_input.trim().to_string();
-
-                        if target_host.is_empty() {
-                            app.log_events.push("Host is empty. Enter IP or domain.".to_string());
-                            continue;
-                        }
-
-                        if scan_task.is_some() {
-                            app.log_events.push("Scan already running".to_string());
-                            continue;
-                        }
-
-                        app.results.clear();
-                        app.total_scanned = 0;
-                        let tx_clone = tx.clone();
-                        let host_for_task = target_host.clone();
-
-                        let handle = tokio::spawn(async move {
-                            scanner::scan_top_ports(&host_for_task, tx_clone).await;
-                        });
-
-                        scan_task = Some(handle);
-                        scan_started_at = Some(Instant::now());
-                        app.is_scanning = true;
-                        app.log_events.push(format!("Top ports scan started for {}", target_host));
-                    }
-
-                    KeyCode::Char('c') => {
-                        if let Some(handle) = scan_task.take() {
-                            handle.abort();
-                            app.is_scanning = false;
-                            let elapsed = scan_started_at.map(|t| t.elapsed()).unwrap_or_default();
-                            app.log_events.push(format!("Scan aborted ({}s)", elapsed.as_secs()));
-                            scan_started_at = None;
-                        } else {
-                            app.log_events.push("No active scan".to_string());
-                        }
-                    }
-
-                    KeyCode::Tab => {
-                        app.input_focus = (app.input_focus + 1) % 3;
-                    }
-
-                    KeyCode::Char(c) => {
-                        app.handle_char_input(c);
-                    }
-
-                    KeyCode::Backspace => {
-                        app.handle_backspace();
-                    }
-
-                    _ => {}
-                }
-            }
-        }
-
-        while let Ok(result) = app.rx.try_recv() {
-            if result.port == 0 && result.status == "DONE" {
-                app.is_scanning = false;
-                if let Some(t0) = scan_started_at.take() {
-                    let elapsed = t0.elapsed();
-                    app.log_events.push(format!("Scan finished in {:.2}s", elapsed.as_secs_f64()));
-                } else {
-                    app.log_events.push("Scan finished".to_string());
-                }
-                scan_task.take();
-            } else {
-                app.results.push(result);
-                app.total_scanned += 1;
-            }
-        }
-
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
+    if let Some(e) = alt_screen_error {
+        app.push_log(format!("Alternate screen unavailable ({}); rendering inline", e));
+    }
+    if let (Some(count), Some(path)) = (services_loaded, &cli.services) {
+        app.push_log(format!("Loaded {} service names from {}", count, path.display()));
+    }
+    if let (Some(count), Some(path)) = (top_ports_loaded, &cli.top_ports_file) {
+        app.push_log(format!("Loaded {} ranked ports from {}", count, path.display()));
+    }
+    match UiPrefs::load(&cli.state) {
+        Ok(prefs) => app.apply_prefs(&prefs),
+        Err(e) => app.push_log(format!("Using default view: {}", e)),
+    }
+    app.operator = config.operator;
+    app.profiles = config.profiles;
+    app.profiles_path = cli.profiles.clone();
+    app.live_path = cli.live_jsonl.clone();
+    app.verbose = cli.verbose;
+    app.all_addresses = cli.all_addresses;
+    app.result_batch = cli.batch.filter(|&n| n > 0);
+    app.latency_closed = cli.latency_closed;
+    app.shuffle = scan_config.shuffle;
+    app.grab_banners = scan_config.max_banner_bytes != Some(0);
+    if let Some(p) = &profile {
+        let idx = app.profiles.iter().position(|q| q == p);
+        if let Some(idx) = idx {
+            app.select_profile(idx);
         }
     }
+    if let Some(host) = &cli.host {
+        app.host_input = host.clone();
+    }
+    if let Some(start) = cli.start {
+        app.start_port_input = start.to_string();
+    }
+    if let Some(end) = cli.end {
+        app.end_port_input = end.to_string();
+    }
+    if let Some(concurrency) = cli.concurrency {
+        app.concurrency_input = concurrency.to_string();
+    }
+    if let Some(pps) = cli.max_pps {
+        app.max_pps_input = pps.to_string();
+    }
+    if let Some(n) = cli.top_count {
+        app.top_count = n as usize;
+    }
+    if let Some(retries) = cli.retries {
+        app.retries_input = retries.to_string();
+    }
+    if let Some(proxy) = cli.proxy {
+        app.proxy_input = proxy.to_string();
+    }
+    // A SIGINT from outside (e.g. `kill -INT`) ends the session like a quit; the scan
+    // task is aborted when the runtime shuts down on return from `main`.
+    let res = tokio::select! {
+        res = tui::run_app(&mut terminal, &mut app, scan_config, tx, trace_rx) => res,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    if !alt_screen {
+        terminal.clear()?;
+    }
+    drop(guard);
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    if let Err(e) = app.prefs().save(&cli.state) {
+        eprintln!("{}", e);
+    }
 
+    res?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+//! Ports module for parsing user-supplied port specifications and the top-ports ranking.
+//! A spec is a comma-separated list of single ports and inclusive ranges,
+//! e.g. `22,80,443` or `20-25,80,8000-8100`. Top-ports scans take the first ports of a
+//! ranked list: `scanner::TOP_PORTS`, unless a list loaded from a file is installed.
+//! # Functions
+//! - `parse_port_spec(spec: &str) -> Result<Vec<u16>, PortSpecError>` - Parses a spec into a sorted, deduplicated port list.
+//! - `format_port_spec(ports: &[u16]) -> String` - Writes a sorted port list back as a compact spec.
+//! - `top_ports(n: usize) -> Vec<u16>` - The `n` highest-ranked ports.
+//! - `load_top_ports(path: &Path) -> io::Result<Vec<u16>>` - Reads a ranked port list, most important first.
+//! - `set_top_ports(ports: Vec<u16>) -> bool` - Installs a loaded ranking for the rest of the process.
+//! # Examples
+//! ```
+//! use night_tool::ports::parse_port_spec;
+//! use night_tool::ports::format_port_spec;
+//! assert_eq!(parse_port_spec("80, 20-22,80").unwrap(), vec![20, 21, 22, 80]);
+//! assert_eq!(format_port_spec(&[20, 21, 22, 80]), "20-22,80");
+//! ```
+
+use crate::scanner::TOP_PORTS;
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+static RANKED: OnceLock<Vec<u16>> = OnceLock::new();
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PortSpecError {
+    #[error("empty port list")]
+    Empty,
+    #[error("invalid port '{0}'")]
+    InvalidPort(String),
+    #[error("invalid range '{0}'")]
+    InvalidRange(String),
+}
+
+fn parse_port(token: &str) -> Result<u16, PortSpecError> {
+    match token.trim().parse::<u16>() {
+        Ok(p) if p > 0 => Ok(p),
+        _ => Err(PortSpecError::InvalidPort(token.trim().to_string())),
+    }
+}
+
+/// Parses a port spec. Repeated ports and overlapping ranges collapse, so every
+/// port appears once and the result is sorted ascending.
+pub fn parse_port_spec(spec: &str) -> Result<Vec<u16>, PortSpecError> {
+    let mut ports = BTreeSet::new();
+
+    for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        match token.split_once('-') {
+            Some((a, b)) => {
+                let (start, end) = (parse_port(a)?, parse_port(b)?);
+                if start > end {
+                    return Err(PortSpecError::InvalidRange(token.to_string()));
+                }
+                ports.extend(start..=end);
+            }
+            None => {
+                ports.insert(parse_port(token)?);
+            }
+        }
+    }
+
+    if ports.is_empty() {
+        return Err(PortSpecError::Empty);
+    }
+    Ok(ports.into_iter().collect())
+}
+
+/// Formats a sorted, deduplicated port list, collapsing consecutive ports into ranges.
+pub fn format_port_spec(ports: &[u16]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < ports.len() {
+        let start = ports[i];
+        while i + 1 < ports.len() && ports[i + 1] == ports[i] + 1 {
+            i += 1;
+        }
+        if ports[i] == start {
+            parts.push(start.to_string());
+        } else {
+            parts.push(format!("{}-{}", start, ports[i]));
+        }
+        i += 1;
+    }
+    parts.join(",")
+}
+
+/// The first `n` ports of the installed ranking, or of `TOP_PORTS` when none is.
+pub fn top_ports(n: usize) -> Vec<u16> {
+    RANKED.get().map_or(TOP_PORTS, Vec::as_slice).iter().take(n).copied().collect()
+}
+
+/// Reads a ranked port list: ports and ranges separated by commas, whitespace or line
+/// breaks, most important first, with `#` comments. Ranges expand in ascending order
+/// and repeats keep their first rank.
+pub fn load_top_ports(path: &Path) -> io::Result<Vec<u16>> {
+    let text = std::fs::read_to_string(path)?;
+    let invalid = |line: usize, e: PortSpecError| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, e));
+    let mut seen = HashSet::new();
+    let mut ranked = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        for token in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+            let ports = parse_port_spec(token).map_err(|e| invalid(i + 1, e))?;
+            ranked.extend(ports.into_iter().filter(|&p| seen.insert(p)));
+        }
+    }
+    if ranked.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no ports listed"));
+    }
+    Ok(ranked)
+}
+
+/// Installs a loaded ranking. Only the first call takes effect; returns whether it did.
+pub fn set_top_ports(ports: Vec<u16>) -> bool {
+    RANKED.set(ports).is_ok()
+}
@@ -1,189 +1,1348 @@
-/// Scanner module for performing asynchronous port scans on a specified host.
-/// It supports scanning a range of ports or a predefined list of common ports,
-/// with configurable concurrency, timeouts, and retry logic.
-/// # Imports
-/// - `crate::services::identify_service` - Function to identify service names based on port numbers.
-/// - `tokio::net::TcpStream` - Tokio's asynchronous TCP stream for network connections.
-/// - `tokio::sync::{mpsc, Semaphore}` - Tokio's multi-producer, single-consumer channel and semaphore for concurrency control.
-/// - `std::sync::Arc` - Atomic reference counting for shared ownership of the semaphore.
-/// - `std::time::{Duration, Instant}` - Standard library time utilities for handling time  
-/// outs and measuring elapsed time.
-/// # Structs
-/// - `ScanResult` - Struct representing the result of a port scan, including port number, status, service name, response time, and optional banner.
-/// # Functions
-/// - `identify_service(port: u16) -> String` - Identifies common services based on their port numbers.
-/// - `scan_range(host: &str, start_port: u16, end_port: u16, tx: mpsc::Sender<ScanResult>)` - Scans a range of ports on the specified host and sends results through the provided channel.
-/// - `scan_top_ports(host: &str, tx: mpsc::Sender<ScanResult>)` - Scans a predefined list of common ports on the specified host and sends results through the provided channel. 
-/// # Examples
-/// ```no_run
-/// use tokio::sync::mpsc;
-/// use crate::scanner::scan_range;
-/// 
-/// #[tokio::main]
-/// async fn main() {
-///     let (tx, mut rx) = mpsc::channel::<ScanResult>(2048);
-///     tokio::spawn(async move {  
-///       scan_range("", 1, 1000, tx).await;
-///     });
-/// }
-/// ```
-
-use crate::services::identify_service;
-use tokio::net::TcpStream;
+//! Scanner module for performing asynchronous port scans on a specified host or CIDR block.
+//! It supports scanning a range of ports or a predefined list of common ports,
+//! with configurable concurrency, timeouts, and retry logic.
+//! # Imports
+//! - `crate::connector::{Connector, DirectConnector, Socks5Connector}` - Pluggable connection setup; direct TCP by default, or through a SOCKS5 proxy.
+//! - `crate::services::identify_service` - Function to identify service names based on port numbers.
+//! - `tokio::sync::{mpsc, Semaphore}` - Tokio's multi-producer, single-consumer channel and semaphore for concurrency control.
+//! - `std::sync::Arc` - Atomic reference counting for shared ownership of the semaphore.
+//! - `std::time::{Duration, Instant}` - Standard library time utilities for handling timeouts and measuring elapsed time.
+//! # Structs
+//! - `ScanResult` - Struct representing the result of a port scan, including port number, status, service name, response time, and optional banner.
+//! - `Ramp` - Step size and interval for ramping concurrency up at scan start.
+//! - `ScanConfig` - Target, ports and tunables such as the startup jitter and probed protocols.
+//! - `UnsentCounts` - Per-status tally of the results `open_only` kept off the channel.
+//! # Enums
+//! - `Protocol` - Transport a result was probed over (TCP or UDP).
+//! - `PortSelection` - The ports `scan` covers: a range, a list, or the top TCP or UDP ports.
+//! - `ScanEvent` - What a scan sends over its channel: results, progress, the end of the scan or a failure.
+//! - `ProgressEvent` - Typed progress updates (`completed`, `total`, `open`) emitted during a scan.
+//! - `BannerRead` - Whether banners are read or peeked.
+//! - `IpFamily` - Address family that won a dual-stack connection race.
+//! # Functions
+//! - `identify_service(port: u16) -> String` - Identifies common services based on their port numbers.
+//! - `probe_for_service(service: &str) -> Option<&'static [u8]>` - Bytes sent to a service to make it reveal a banner.
+//! - `scan(config: ScanConfig, tx: mpsc::Sender<ScanEvent>)` - Scans `config.ports` on `config.host`.
+//! - `scan_range(host: &str, start_port: u16, end_port: u16, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>)` - Scans a range of ports on the specified host and sends results through the provided channel.
+//! - `scan_ports(host: &str, ports: Vec<u16>, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>)` - Scans an explicit, deduplicated list of ports.
+//! - `scan_top_udp_ports(host: &str, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>)` - Scans common UDP services, sending protocol-specific probes to DNS, NTP and SNMP.
+//! - `parse_targets(host: &str) -> Vec<IpAddr>` - Expands a single IP (IPv6 optionally bracketed) or a CIDR block into the addresses it covers.
+//! - `resolve_all(host: &str) -> io::Result<Vec<IpAddr>>` - Resolves every distinct address of a hostname.
+//! - `scan_top_ports(host: &str, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>)` - Scans the top-ports ranking (built-in or loaded) on the specified host and sends results through the provided channel.
+//! # Examples
+//! ```no_run
+//! use tokio::sync::mpsc;
+//! use night_tool::scanner::{scan, PortSelection, ScanConfig, ScanEvent};
+//! 
+//! #[tokio::main]
+//! async fn main() {
+//!     let (tx, mut rx) = mpsc::channel::<ScanEvent>(2048);
+//!     let config = ScanConfig { host: "10.0.0.1".to_string(), ports: PortSelection::Range(1, 1000), ..Default::default() };
+//!     tokio::spawn(scan(config, tx));
+//!     while let Some(event) = rx.recv().await {
+//!         match event {
+//!             ScanEvent::Result(r) => println!("{}/{} {}", r.port, r.protocol, r.status),
+//!             ScanEvent::Error(e) => eprintln!("{}", e),
+//!             ScanEvent::Finished { .. } => break,
+//!             ScanEvent::Progress { .. } => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::connector::{BoxedStream, Connector, DirectConnector, Socks5Connector};
+use crate::discovery;
+use crate::ports::top_ports;
+use crate::services::{identify_service, identify_service_for, parse_version, Software};
+use crate::tls;
 use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::io::AsyncReadExt;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
 
-#[derive(Clone, Debug)]
+/// Transport a port is probed over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// IP family a dual-stack connection was made over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum IpFamily {
+    #[serde(rename = "ipv4")]
+    V4,
+    #[serde(rename = "ipv6")]
+    V6,
+}
+
+impl std::fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpFamily::V4 => write!(f, "ipv4"),
+            IpFamily::V6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct ScanResult {
+    /// Host or address this result was probed on.
+    pub host: String,
     pub port: u16,
+    pub protocol: Protocol,
     pub status: String,
     pub service: String,
     pub response_ms: u128,
     pub banner: Option<String>,
+    /// Family that won the happy-eyeballs race, when one was run.
+    pub family: Option<IpFamily>,
+    /// The port accepted the connection but sent no greeting before the banner read
+    /// timed out. Connect timeouts are reported as the "filtered" status instead.
+    pub read_timed_out: bool,
+    /// Exact socket error behind a "closed", "reset", "unreachable" or "error" result,
+    /// as reported by the OS.
+    pub error: Option<String>,
+    /// Probes sent before this result was reported: 1 unless silent attempts were retried.
+    pub attempts: u8,
+    /// Product and version read from the banner (`services::parse_version`), e.g.
+    /// `OpenSSH` `8.9p1`.
+    pub software: Option<Software>,
+}
+
+/// A message from a running scan. Every scan ends with exactly one `Finished`.
+#[derive(Clone, Debug)]
+pub enum ScanEvent {
+    Result(ScanResult),
+    /// Probes finished out of all the scan covers, hosts skipped by `skip_dead_hosts`
+    /// included. Sent every `ScanConfig::progress_interval` (dropped rather than queued
+    /// when the channel is full) and once more, always delivered, before `Finished`.
+    Progress { done: usize, total: usize },
+    /// The last message of a scan. `cancelled` is set when `ScanConfig::cancel` stopped
+    /// it early, `timed_out` when it ran into `ScanConfig::deadline`.
+    Finished { elapsed: Duration, cancelled: bool, timed_out: bool },
+    /// The scan could not run, e.g. its target did not resolve. No probes are sent,
+    /// and `Finished` follows.
+    Error(String),
+}
+
+/// How the banner of an open TCP port is captured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BannerRead {
+    /// Consume the greeting with a regular read.
+    #[default]
+    Read,
+    /// Peek at the greeting without consuming it, so stateful protocols are not
+    /// disturbed. Streams that cannot peek fall back to `Read`.
+    Peek,
+}
+
+/// Progress of a running scan, reported separately from results so embedders can
+/// drive their own progress display. Counts are per (target, port, protocol) probe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// Sent every `ScanConfig::progress_interval` while the scan runs.
+    Progress { completed: usize, total: usize, open: usize },
+    /// Sent once after the last probe, before `ScanEvent::Finished`.
+    Finished { completed: usize, total: usize, open: usize },
+}
+
+/// Default cadence of `ProgressEvent::Progress`.
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a paused scan checks whether it was resumed or cancelled.
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// The ports `scan` covers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PortSelection {
+    /// Every port from the first to the second, inclusive.
+    Range(u16, u16),
+    /// An explicit list, e.g. from `ports::parse_port_spec`. Repeats are probed once.
+    List(Vec<u16>),
+    /// The whole top-ports ranking (`ports::top_ports`), with the top-ports defaults
+    /// (see `scan_top_ports`).
+    #[default]
+    Top,
+    /// The `n` highest-ranked top ports, with the top-ports defaults.
+    TopN(usize),
+    /// `TOP_UDP_PORTS` over UDP only (see `scan_top_udp_ports`).
+    TopUdp,
+}
+
+impl PortSelection {
+    /// Ports probed per target and protocol.
+    pub fn port_count(&self) -> usize {
+        match self {
+            PortSelection::Range(start, end) => (usize::from(*end) + 1).saturating_sub(usize::from(*start)),
+            PortSelection::List(ports) => ports.iter().collect::<std::collections::HashSet<_>>().len(),
+            PortSelection::Top => top_ports(usize::MAX).len(),
+            PortSelection::TopN(n) => top_ports(*n).len(),
+            PortSelection::TopUdp => TOP_UDP_PORTS.len(),
+        }
+    }
+
+    /// Connect timeout used when `ScanConfig::timeout` is unset.
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            PortSelection::Range(..) | PortSelection::List(_) => RANGE_TIMEOUT,
+            PortSelection::Top | PortSelection::TopN(_) | PortSelection::TopUdp => TOP_PORTS_TIMEOUT,
+        }
+    }
+}
+
+/// Gradual opening of the concurrency cap: the scan starts with `step` probes in
+/// flight and gains another `step` every `interval` until the cap is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ramp {
+    pub step: usize,
+    pub interval: Duration,
+}
+
+/// Results `ScanConfig::open_only` counted instead of sending, by status.
+#[derive(Debug, Default)]
+pub struct UnsentCounts {
+    pub closed: AtomicUsize,
+    /// "filtered" results (silent ports).
+    pub filtered: AtomicUsize,
+    /// Everything else: reset, unreachable and error results.
+    pub other: AtomicUsize,
+}
+
+impl UnsentCounts {
+    fn record(&self, status: &str) {
+        let counter = match status {
+            "closed" => &self.closed,
+            "filtered" => &self.filtered,
+            _ => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// (closed, filtered, other) so far.
+    pub fn snapshot(&self) -> (usize, usize, usize) {
+        (self.closed.load(Ordering::Relaxed), self.filtered.load(Ordering::Relaxed), self.other.load(Ordering::Relaxed))
+    }
+}
+
+/// Tunables shared by all scan modes, plus the target and ports used by `scan`.
+#[derive(Clone, Debug, Default)]
+pub struct ScanConfig {
+    /// Host, address or CIDR block `scan` probes. The other scan functions take it as
+    /// an argument and ignore this field.
+    pub host: String,
+    /// Ports `scan` probes; ignored by the other scan functions.
+    pub ports: PortSelection,
+    /// Upper bound of a random delay applied before the first connect of each
+    /// initial worker, so the opening burst ramps up instead of spiking.
+    pub start_jitter: Option<Duration>,
+    /// How connections are opened. `None` connects directly with `DirectConnector`.
+    pub connector: Option<Arc<dyn Connector>>,
+    /// SOCKS5 proxy TCP probes connect through (`Socks5Connector`), e.g. a jump box's
+    /// `ssh -D` port. Hostnames are resolved by the proxy. Ignored when a custom
+    /// `connector` is set; UDP probes never use it.
+    pub proxy: Option<SocketAddr>,
+    /// Protocols probed for every port; each gets its own task. Empty means TCP only.
+    pub protocols: Vec<Protocol>,
+    /// Verbose trace of every connection attempt. Entries are dropped rather than
+    /// queued when the channel is full, which caps how fast the trace can flood.
+    pub trace: Option<mpsc::Sender<String>>,
+    /// Max simultaneous probes. `None` keeps the scan mode's default (256 for ranges, 128 for top ports).
+    pub concurrency: Option<usize>,
+    pub banner_read: BannerRead,
+    /// Largest banner kept per port, in bytes. `None` uses `DEFAULT_MAX_BANNER_BYTES`.
+    /// Longer banners are cut and end with `BANNER_TRUNCATED`. `Some(0)` skips banner
+    /// grabbing on TCP altogether: open ports are closed as soon as they connect.
+    pub max_banner_bytes: Option<usize>,
+    /// On well-known ports (FTP, SSH, SMTP, POP3, IMAP), keep reading until the
+    /// protocol's greeting is complete, e.g. the last line of a multi-line SMTP
+    /// `220-` greeting, instead of settling for the first chunk received.
+    pub greeting_aware: bool,
+    /// Ports that may receive active probes (the HTTP request on web ports, the DNS,
+    /// NTP and SNMP requests over UDP). Empty allows every port not in `probe_deny`.
+    pub probe_allow: Vec<u16>,
+    /// Ports that never receive active probes, e.g. fragile embedded or OT services.
+    /// They still get a passive banner read (and an empty datagram over UDP).
+    pub probe_deny: Vec<u16>,
+    /// For hosts resolving to both IPv4 and IPv6, connect over both at once and keep
+    /// whichever succeeds first. Doubles connection attempts, so it is off by default.
+    /// Ignored when a custom `connector` or a `proxy` is set.
+    pub happy_eyeballs: bool,
+    /// Ramp concurrency up to its cap instead of allowing it all at once.
+    pub ramp: Option<Ramp>,
+    /// Connect timeout per attempt. `None` keeps the scan mode's default
+    /// (`RANGE_TIMEOUT` or `TOP_PORTS_TIMEOUT`).
+    pub timeout: Option<Duration>,
+    /// Extra attempts for a port that stays silent. `None` keeps the scan mode's
+    /// default (1 for ranges, 0 for top ports); values above `MAX_RETRIES` are capped.
+    pub retries: Option<u8>,
+    /// Wait before the first retry; it doubles for each one after. `None` uses
+    /// `DEFAULT_RETRY_BACKOFF`.
+    pub retry_backoff: Option<Duration>,
+    /// How long an open TCP port may stay silent before the banner read gives up.
+    /// `None` keeps the scan mode's default (500ms for ranges, 1500ms for top ports).
+    pub banner_timeout: Option<Duration>,
+    /// Hard upper bound on the whole banner phase of any port, whatever the scan
+    /// mode or `banner_timeout` say. The worker keeps the bytes read so far and
+    /// frees its concurrency slot.
+    pub banner_hard_cap: Option<Duration>,
+    /// Probe these addresses instead of `host`, reporting results per address. Filled
+    /// from `resolve_all` to compare every backend behind a hostname.
+    pub addresses: Vec<IpAddr>,
+    /// Probe ports in random order instead of ascending, so the scan doesn't read as
+    /// a sequential sweep. Results arrive out of order either way.
+    pub shuffle: bool,
+    /// Ping every target address first and skip hosts that don't answer (see
+    /// `discovery`). Names left unresolved (custom connector, happy eyeballs) are always scanned.
+    /// Ignored with a `proxy`, since pings can't go through it.
+    pub skip_dead_hosts: bool,
+    /// Most connection attempts (retries included) started per second across the
+    /// whole scan. `None` leaves the rate bounded only by `concurrency`.
+    pub max_pps: Option<u32>,
+    /// Seed for `shuffle`, making the order reproducible across runs. `None` seeds randomly.
+    pub seed: Option<u64>,
+    /// Send only results worth looking at: anything but "open" and "open|filtered" is
+    /// counted in `probed` but never sent, which keeps channel traffic low on large scans.
+    pub open_only: bool,
+    /// Incremented once per finished probe, whether or not its result was sent.
+    pub probed: Option<Arc<AtomicUsize>>,
+    /// Tallies the results `open_only` didn't send, so callers can still count closed
+    /// and filtered ports. Hosts skipped by `skip_dead_hosts` are not included.
+    pub unsent: Option<Arc<UnsentCounts>>,
+    /// End the scan once this many "open" results have been sent: no new probes start,
+    /// probes in flight are dropped and `Finished` follows at once. Other states don't count.
+    pub stop_after_open: Option<usize>,
+    /// Set to stop the scan: no new probes start, probes already in flight finish and
+    /// are sent, and the scan ends with a cancelled `ScanEvent::Finished`.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// While set, no new probes start; probes already in flight finish. Clearing it
+    /// resumes the scan where it stopped.
+    pub pause: Option<Arc<AtomicBool>>,
+    /// Receives progress events while the scan runs. Periodic events are dropped
+    /// rather than queued when the channel is full; `Finished` is always delivered.
+    pub progress: Option<mpsc::Sender<ProgressEvent>>,
+    /// Wall-clock limit on the whole scan: name resolution, host discovery and the
+    /// sweep share it. When it passes, whichever phase is running is abandoned (probes
+    /// in flight are aborted) and `Finished` follows with `timed_out` set and whatever
+    /// results were sent by then.
+    pub deadline: Option<Duration>,
+    /// Cadence of periodic progress events, both `ProgressEvent`s and
+    /// `ScanEvent::Progress`. `None` uses `DEFAULT_PROGRESS_INTERVAL`.
+    pub progress_interval: Option<Duration>,
+}
+
+impl ScanConfig {
+    fn connector(&self) -> &dyn Connector {
+        self.connector.as_deref().unwrap_or(&DirectConnector)
+    }
+
+    fn trace(&self, msg: impl FnOnce() -> String) {
+        if let Some(tx) = &self.trace {
+            let _ = tx.try_send(msg());
+        }
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    fn paused(&self) -> bool {
+        self.pause.as_ref().is_some_and(|p| p.load(Ordering::Relaxed))
+    }
+
+    /// Whether active probe bytes may be sent to `port`.
+    fn may_probe(&self, port: u16) -> bool {
+        !self.probe_deny.contains(&port) && (self.probe_allow.is_empty() || self.probe_allow.contains(&port))
+    }
+
+    fn max_banner_bytes(&self) -> usize {
+        self.max_banner_bytes.unwrap_or(DEFAULT_MAX_BANNER_BYTES)
+    }
+
+    fn protocols(&self) -> &[Protocol] {
+        if self.protocols.is_empty() {
+            &[Protocol::Tcp]
+        } else {
+            &self.protocols
+        }
+    }
+}
+
+/// Enough for a typical greeting or a full set of HTTP response headers.
+pub const DEFAULT_MAX_BANNER_BYTES: usize = 4096;
+
+/// Appended to banners that were cut at the configured limit.
+pub const BANNER_TRUNCATED: &str = " [truncated]";
+
+/// Decodes a captured banner, cutting it to `limit` bytes and marking the cut.
+fn capture_banner(bytes: &[u8], limit: usize) -> String {
+    if bytes.len() > limit {
+        let mut banner = String::from_utf8_lossy(&bytes[..limit]).trim().to_string();
+        banner.push_str(BANNER_TRUNCATED);
+        banner
+    } else {
+        String::from_utf8_lossy(bytes).trim().to_string()
+    }
+}
+
+/// Default connect timeout for `scan_range`.
+pub const RANGE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Default connect timeout for `scan_top_ports`.
+pub const TOP_PORTS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most retries `ScanConfig::retries` allows; each one can add a full timeout (plus
+/// the backoff) to every silent port.
+pub const MAX_RETRIES: u8 = 5;
+
+/// Default wait before the first retry of a silent port.
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Per-mode defaults for settings a `ScanConfig` leaves unset.
+#[derive(Clone, Copy)]
+struct ScanMode {
+    concurrency: usize,
+    timeout: Duration,
+    retries: u8,
+    banner_timeout: Duration,
 }
 
-const TOP_PORTS: &[u16] = &[
-    21, 22, 23, 25, 53, 80, 110, 143, 443, 445, 3306, 3389, 5432, 5900, 8080, 8443, 9200,
+/// Full ranges favour speed: a short banner read keeps silent ports cheap.
+const RANGE_MODE: ScanMode = ScanMode {
+    concurrency: 256,
+    timeout: RANGE_TIMEOUT,
+    retries: 1,
+    banner_timeout: Duration::from_millis(500),
+};
+
+/// Top-port scans cover few ports, so they can wait longer for slow greetings.
+const TOP_PORTS_MODE: ScanMode = ScanMode {
+    concurrency: 128,
+    timeout: TOP_PORTS_TIMEOUT,
+    retries: 0,
+    banner_timeout: Duration::from_millis(1500),
+};
+
+/// Built-in top-ports ranking, most common first: nmap's top 100 TCP ports by
+/// frequency, then database and search services it ranks lower. A list loaded with
+/// `ports::set_top_ports` replaces it.
+pub const TOP_PORTS: &[u16] = &[
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993, 5900,
+    1025, 587, 8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000, 8443, 8000, 32768, 554,
+    26, 1433, 49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631, 631, 49153, 8081, 2049, 88, 79, 5800, 106,
+    2121, 1110, 49155, 6000, 513, 990, 5357, 427, 49156, 543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009,
+    7070, 5190, 3000, 5432, 1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+    1521, 6379, 9200, 27017, 5601,
 ];
 
-async fn scan_port_once(host: &str, port: u16, timeout: Duration) -> ScanResult {
-    let addr = format!("{}:{}", host, port);
+/// Ports probed by `scan_top_udp_ports`.
+pub const TOP_UDP_PORTS: &[u16] = &[53, 67, 69, 123, 137, 161, 500, 514, 1900, 5353];
+
+/// DNS standard query for the root NS records.
+const DNS_PROBE: &[u8] = &[
+    0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+];
+
+/// NTPv3 client request: LI 0, version 3, mode 3, all other fields zero.
+const NTP_PROBE: &[u8] = &[
+    0x1b, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// SNMPv1 GetRequest for sysDescr.0 with community `public`.
+const SNMP_PROBE: &[u8] = &[
+    0x30, 0x26, 0x02, 0x01, 0x00, 0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', 0xa0, 0x19, 0x02, 0x01, 0x01,
+    0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01,
+    0x01, 0x00, 0x05, 0x00,
+];
+
+/// Datagram sent to a UDP port. Most UDP services ignore an empty datagram, so
+/// well-known ones get a minimal valid request that they answer.
+fn udp_probe(port: u16) -> &'static [u8] {
+    match port {
+        53 | 5353 => DNS_PROBE,
+        123 => NTP_PROBE,
+        161 => SNMP_PROBE,
+        _ => &[],
+    }
+}
+
+/// Races a connection over the first IPv6 and first IPv4 address of `host`, returning
+/// the first to succeed, or the last error if both fail. Returns `None` when the host
+/// does not resolve to both families.
+async fn connect_dual_stack(host: &str, port: u16) -> Option<io::Result<(BoxedStream, IpFamily)>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await.ok()?.collect();
+    let v6 = addrs.iter().find(|a| a.is_ipv6()).copied()?;
+    let v4 = addrs.iter().find(|a| a.is_ipv4()).copied()?;
+
+    let attempt = |addr: SocketAddr, family: IpFamily| async move {
+        let stream = TcpStream::connect(addr).await?;
+        let _ = stream.set_nodelay(true);
+        Ok((Box::new(stream) as BoxedStream, family))
+    };
+    let v6_attempt = attempt(v6, IpFamily::V6);
+    let v4_attempt = attempt(v4, IpFamily::V4);
+    tokio::pin!(v6_attempt, v4_attempt);
+
+    Some(tokio::select! {
+        res = &mut v6_attempt => match res {
+            Ok(won) => Ok(won),
+            Err(_) => v4_attempt.await,
+        },
+        res = &mut v4_attempt => match res {
+            Ok(won) => Ok(won),
+            Err(_) => v6_attempt.await,
+        },
+    })
+}
+
+async fn connect_tcp(target: &Target, port: u16, config: &ScanConfig) -> io::Result<(BoxedStream, Option<IpFamily>)> {
+    if config.connector.is_none() {
+        if let Some(proxy) = config.proxy {
+            return Socks5Connector::new(proxy).connect(&target.name, port).await.map(|stream| (stream, None));
+        }
+        if config.happy_eyeballs && let Some(res) = connect_dual_stack(&target.name, port).await {
+            return res.map(|(stream, family)| (stream, Some(family)));
+        }
+        if let Some(ip) = target.addr {
+            let stream = TcpStream::connect(SocketAddr::new(ip, port)).await?;
+            let _ = stream.set_nodelay(true);
+            return Ok((Box::new(stream), None));
+        }
+    }
+    config.connector().connect(&target.name, port).await.map(|stream| (stream, None))
+}
+
+/// How a well-known protocol marks the end of its greeting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Greeting {
+    /// A single line (SSH, POP3, IMAP).
+    Line,
+    /// SMTP/FTP style replies: `220-` lines continue, the first `220 ` line ends it.
+    ReplyCode,
+    /// Everything up to the peer closing the connection (an HTTP/1.0 response).
+    UntilClose,
+}
+
+fn greeting_for(port: u16) -> Option<Greeting> {
+    match port {
+        21 | 25 | 587 => Some(Greeting::ReplyCode),
+        22 | 110 | 143 => Some(Greeting::Line),
+        _ => None,
+    }
+}
+
+fn greeting_complete(greeting: Greeting, bytes: &[u8]) -> bool {
+    let mut lines = bytes.split_inclusive(|&b| b == b'\n').filter(|l| l.ends_with(b"\n"));
+    match greeting {
+        Greeting::Line => lines.next().is_some(),
+        Greeting::ReplyCode => lines.any(|l| l.len() >= 4 && l[..3].iter().all(u8::is_ascii_digit) && l[3] == b' '),
+        Greeting::UntilClose => false,
+    }
+}
+
+/// Reads until the greeting is complete, the buffer is full, the peer closes or
+/// `timeout` runs out. Bytes received before a timeout are kept; `None` means
+/// nothing arrived at all.
+async fn read_greeting<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    buf: &mut [u8],
+    greeting: Greeting,
+    timeout: Duration,
+) -> Option<io::Result<usize>> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buf[filled..])).await {
+            Ok(Ok(0)) => break,
+            Ok(Ok(n)) => {
+                filled += n;
+                if greeting_complete(greeting, &buf[..filled]) {
+                    break;
+                }
+            }
+            Ok(Err(e)) if filled == 0 => return Some(Err(e)),
+            Ok(Err(_)) => break,
+            Err(_) if filled == 0 => return None,
+            Err(_) => break,
+        }
+    }
+    Some(Ok(filled))
+}
+
+/// Classifies a failed TCP connect. A refusal (RST to our SYN) means nothing listens;
+/// a reset or ICMP unreachable points at the network path or a firewall instead.
+fn connect_error_status(e: &io::Error) -> &'static str {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused => "closed",
+        io::ErrorKind::ConnectionReset => "reset",
+        io::ErrorKind::HostUnreachable | io::ErrorKind::NetworkUnreachable => "unreachable",
+        _ => "error",
+    }
+}
+
+/// HTTPS ports get their certificate summarized instead of an HTTP request.
+fn is_tls_service(port: u16) -> bool {
+    matches!(identify_service(port).as_str(), "https" | "https-alt")
+}
+
+/// Web servers say nothing until asked, so ports named `http*` (http, https,
+/// http-alt, http-proxy, ...) get an HTTP request instead of a passive read.
+fn is_http_service(port: u16) -> bool {
+    identify_service(port).starts_with("http")
+}
+
+/// Sends `GET / HTTP/1.0` and reads the response until the server closes the
+/// connection, `buf` is full or `timeout` runs out. Same contract as `read_greeting`.
+async fn probe_http(stream: &mut BoxedStream, host: &str, buf: &mut [u8], timeout: Duration) -> Option<io::Result<usize>> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let host = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+    let request = format!("GET / HTTP/1.0\r\nHost: {}\r\n\r\n", host);
+    match tokio::time::timeout_at(deadline, stream.write_all(request.as_bytes())).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Some(Err(e)),
+        Err(_) => return None,
+    }
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    read_greeting(stream, buf, Greeting::UntilClose, remaining).await
+}
+
+/// Bytes that coax a banner out of `service` (a name from `identify_service`), or
+/// `None` to read passively. Line-based mail and file servers answer an empty line
+/// with a reply that carries their name and often their version; HTTP-based APIs
+/// need a request. Web ports proper (`http*`) are probed by `probe_http` instead,
+/// which also sends a `Host` header.
+pub fn probe_for_service(service: &str) -> Option<&'static [u8]> {
+    match service {
+        "ftp" | "smtp" | "pop3" | "imap" | "telnet" => Some(b"\r\n"),
+        "elasticsearch" => Some(b"GET / HTTP/1.0\r\n\r\n"),
+        _ => None,
+    }
+}
+
+/// Reads a banner without sending anything: the full greeting on ports with a known
+/// one when `greeting_aware`, else a single read. Same contract as `read_greeting`.
+async fn read_banner(stream: &mut BoxedStream, port: u16, buf: &mut [u8], greeting_aware: bool, timeout: Duration) -> Option<io::Result<usize>> {
+    match greeting_for(port).filter(|_| greeting_aware) {
+        Some(greeting) => read_greeting(stream, buf, greeting, timeout).await,
+        None => tokio::time::timeout(timeout, stream.read(buf)).await.ok(),
+    }
+}
+
+/// Sends `probe`, then reads the reply like `read_banner`, all within `timeout`.
+async fn probe_and_read(
+    stream: &mut BoxedStream,
+    probe: &[u8],
+    port: u16,
+    buf: &mut [u8],
+    greeting_aware: bool,
+    timeout: Duration,
+) -> Option<io::Result<usize>> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    match tokio::time::timeout_at(deadline, stream.write_all(probe)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Some(Err(e)),
+        Err(_) => return None,
+    }
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    read_banner(stream, port, buf, greeting_aware, remaining).await
+}
+
+/// Text of the first `<title>` element, with whitespace collapsed.
+fn html_title(body: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets, so indices into `lower` are valid in `body`.
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = body[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Condenses an HTTP response into `status line | Server: ... | Title: ...`.
+/// Returns `None` when the bytes are not an HTTP response (e.g. a TLS alert).
+fn http_banner(bytes: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(bytes);
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((&text, ""));
+    let mut lines = head.lines();
+    let status = lines.next()?.trim();
+    if !status.starts_with("HTTP/") {
+        return None;
+    }
+    let mut parts = vec![status.to_string()];
+    let server = lines.find_map(|l| l.split_once(':').filter(|(k, _)| k.trim().eq_ignore_ascii_case("server")));
+    if let Some((_, server)) = server {
+        parts.push(format!("Server: {}", server.trim()));
+    }
+    if let Some(title) = html_title(body) {
+        parts.push(format!("Title: {}", title));
+    }
+    Some(parts.join(" | "))
+}
+
+async fn scan_port_once(target: &Target, port: u16, timeout: Duration, config: &ScanConfig) -> ScanResult {
     let start = Instant::now();
+    let host = target.name.as_str();
     
-    match tokio::time::timeout(timeout, TcpStream::connect(&addr)).await {
-        Ok(Ok(mut stream)) => {
-            let mut buf = vec![0u8; 1024];
-            let _ = stream.set_nodelay(true);
-            let read_res = tokio::time::timeout(Duration::from_millis(500), stream.read(&mut buf)).await;
-            
-            let banner = match read_res {
-                Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).trim().to_string()),
-                _ => None,
+    match tokio::time::timeout(timeout, connect_tcp(target, port, config)).await {
+        Ok(Ok((mut stream, family))) => {
+            // One spare byte tells a banner of exactly `limit` bytes from a longer one.
+            let limit = config.max_banner_bytes();
+            let mut buf = vec![0u8; limit + 1];
+            let mut read_timeout = config.banner_timeout.unwrap_or(RANGE_MODE.banner_timeout);
+            if let Some(cap) = config.banner_hard_cap {
+                read_timeout = read_timeout.min(cap);
+            }
+            let grab = limit > 0;
+            let mut peeked = None;
+            if grab && config.banner_read == BannerRead::Peek && let Some(fut) = stream.peek(&mut buf) {
+                peeked = Some(tokio::time::timeout(read_timeout, fut).await);
+            }
+            // Peek mode never sends anything, so it never probes.
+            let active = grab && config.banner_read == BannerRead::Read && config.may_probe(port);
+            let tls = active && is_tls_service(port);
+            let http = active && !tls && is_http_service(port);
+            let nudge = if active && !tls && !http { probe_for_service(&identify_service(port)) } else { None };
+            let (banner, read_timed_out) = if !grab {
+                (None, false)
+            } else if tls {
+                // A failed handshake (e.g. a TLS 1.3-only server) leaves a plain open result.
+                let sni = host.parse::<IpAddr>().is_err().then_some(host);
+                let cert = tls::inspect_certificate(&mut stream, sni, read_timeout).await;
+                (cert.map(|c| c.to_string()).filter(|s| !s.is_empty()), false)
+            } else {
+                // `None` means the read timed out before any byte arrived.
+                let read_res = match (peeked, nudge) {
+                    (Some(res), _) => res.ok(),
+                    (None, _) if http => probe_http(&mut stream, host, &mut buf, read_timeout).await,
+                    (None, Some(probe)) => probe_and_read(&mut stream, probe, port, &mut buf, config.greeting_aware, read_timeout).await,
+                    (None, None) => read_banner(&mut stream, port, &mut buf, config.greeting_aware, read_timeout).await,
+                };
+                let banner = match read_res {
+                    Some(Ok(n)) if n > 0 => {
+                        // http_banner rejects anything that is not an HTTP response.
+                        let summary = if http || nudge.is_some() { http_banner(&buf[..n]) } else { None };
+                        Some(summary.unwrap_or_else(|| capture_banner(&buf[..n], limit)))
+                    }
+                    _ => None,
+                };
+                (banner, read_res.is_none())
             };
             
             let elapsed = start.elapsed().as_millis();
             ScanResult {
+                host: host.to_string(),
                 port,
+                protocol: Protocol::Tcp,
                 status: "open".to_string(),
                 service: identify_service(port),
                 response_ms: elapsed,
                 banner,
+                family,
+                read_timed_out,
+                error: None,
+                attempts: 1,
+                software: None,
             }
         }
-        Ok(Err(_)) => {
+        Ok(Err(e)) => {
             let elapsed = start.elapsed().as_millis();
             ScanResult {
+                host: host.to_string(),
                 port,
-                status: "closed".to_string(),
+                protocol: Protocol::Tcp,
+                status: connect_error_status(&e).to_string(),
                 service: identify_service(port),
                 response_ms: elapsed,
                 banner: None,
+                family: None,
+                read_timed_out: false,
+                error: Some(e.to_string()),
+                attempts: 1,
+                software: None,
             }
         }
         Err(_) => ScanResult {
+            host: host.to_string(),
             port,
-            status: "timeout".to_string(),
+            protocol: Protocol::Tcp,
+            status: "filtered".to_string(),
             service: identify_service(port),
             response_ms: timeout.as_millis(),
             banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
         },
     }
 }
 
-async fn scan_with_retries(host: &str, port: u16, base_timeout: Duration, retries: u8) -> ScanResult {
-    let mut backoff = Duration::from_millis(100);
-    
-    for _ in 0..=retries {
-        let res = scan_port_once(host, port, base_timeout).await;
-        if res.status == "open" || res.status == "closed" {
-            return res;
+/// Sends an empty datagram and waits for a reply. A reply means "open", an ICMP
+/// port-unreachable (surfaced as `ConnectionRefused`) means "closed", and silence
+/// is reported as "open|filtered" since UDP services often ignore empty probes.
+async fn scan_udp_once(target: &Target, port: u16, timeout: Duration, config: &ScanConfig) -> ScanResult {
+    let start = Instant::now();
+    let mut result = ScanResult {
+        host: target.name.clone(),
+        port,
+        protocol: Protocol::Udp,
+        status: "open|filtered".to_string(),
+        service: identify_service_for(port, Protocol::Udp),
+        response_ms: timeout.as_millis(),
+        banner: None,
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: None,
+    };
+
+    let resolved = match target.addr {
+        Some(ip) => Ok(Some(SocketAddr::new(ip, port))),
+        None => tokio::net::lookup_host((target.name.as_str(), port)).await.map(|mut a| a.next()),
+    };
+    let addr = match resolved {
+        Ok(Some(addr)) => addr,
+        Ok(None) => {
+            result.status = "error".to_string();
+            result.error = Some("no address resolved".to_string());
+            return result;
+        }
+        Err(e) => {
+            result.status = "error".to_string();
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    let bind = if addr.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(s) => s,
+        Err(e) => {
+            result.status = "error".to_string();
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    let sent = match socket.connect(addr).await {
+        Ok(()) => {
+            let payload = if config.may_probe(port) { udp_probe(port) } else { &[] };
+            socket.send(payload).await.map(|_| ())
         }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = sent {
+        result.status = "closed".to_string();
+        result.error = Some(e.to_string());
+        result.response_ms = start.elapsed().as_millis();
+        return result;
+    }
+
+    let mut buf = vec![0u8; 1024];
+    match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+        Ok(Ok(n)) => {
+            result.status = "open".to_string();
+            result.response_ms = start.elapsed().as_millis();
+            if n > 0 {
+                result.banner = Some(String::from_utf8_lossy(&buf[..n]).trim().to_string());
+            }
+        }
+        Ok(Err(e)) => {
+            result.status = "closed".to_string();
+            result.response_ms = start.elapsed().as_millis();
+            result.error = Some(e.to_string());
+        }
+        Err(_) => {}
+    }
+    result
+}
+
+async fn scan_port_proto(target: &Target, port: u16, protocol: Protocol, timeout: Duration, config: &ScanConfig) -> ScanResult {
+    match protocol {
+        Protocol::Tcp => scan_port_once(target, port, timeout, config).await,
+        Protocol::Udp => scan_udp_once(target, port, timeout, config).await,
+    }
+}
+
+/// Spaces connection attempts at least `1 / pps` apart across every task of a scan.
+struct RateLimiter(tokio::sync::Mutex<tokio::time::Interval>);
+
+impl RateLimiter {
+    fn new(pps: u32) -> Self {
+        let mut interval = tokio::time::interval(Duration::from_secs(1) / pps.max(1));
+        // After an idle stretch, resume at the steady rate instead of bursting to catch up.
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Self(tokio::sync::Mutex::new(interval))
+    }
+
+    async fn wait(&self) {
+        self.0.lock().await.tick().await;
+    }
+}
+
+/// How conclusive a status is; a higher rank wins when merging attempts.
+fn outcome_rank(status: &str) -> u8 {
+    match status {
+        "open" => 3,
+        "closed" | "reset" => 2,
+        "filtered" => 0,
+        _ => 1,
+    }
+}
+
+/// Folds one attempt into the best result so far: the more conclusive status wins,
+/// ties keep the faster response, and a captured banner survives either way. The
+/// merged result counts every attempt, not just the winning one.
+fn merge_attempt(best: Option<ScanResult>, res: ScanResult) -> ScanResult {
+    let Some(best) = best else { return res };
+    let attempts = res.attempts;
+    let (mut winner, loser) = match outcome_rank(&res.status).cmp(&outcome_rank(&best.status)) {
+        std::cmp::Ordering::Greater => (res, best),
+        std::cmp::Ordering::Less => (best, res),
+        std::cmp::Ordering::Equal if res.response_ms < best.response_ms => (res, best),
+        std::cmp::Ordering::Equal => (best, res),
+    };
+    if winner.banner.is_none() && winner.status == loser.status {
+        winner.banner = loser.banner;
+    }
+    winner.attempts = attempts;
+    winner
+}
+
+/// Scans one port, retrying silent (`filtered`) attempts up to `retries` times with
+/// exponential backoff.
+/// Attempts stop at the first answer; the reported result merges every attempt made.
+async fn scan_with_retries(
+    target: &Target,
+    port: u16,
+    protocol: Protocol,
+    base_timeout: Duration,
+    retries: u8,
+    config: &ScanConfig,
+    limiter: Option<&RateLimiter>,
+) -> ScanResult {
+    let mut backoff = config.retry_backoff.unwrap_or(DEFAULT_RETRY_BACKOFF);
+    let trace_attempt = |attempt: u8, res: &ScanResult| {
+        config.trace(|| match &res.error {
+            Some(e) => format!("{}/{} attempt {}: {} in {}ms ({})", port, protocol, attempt, res.status, res.response_ms, e),
+            None => format!("{}/{} attempt {}: {} in {}ms", port, protocol, attempt, res.status, res.response_ms),
+        });
+    };
+    let mut best = None;
+    let mut attempt = 1;
+    loop {
+        if let Some(limiter) = limiter {
+            limiter.wait().await;
+        }
+        let mut res = scan_port_proto(target, port, protocol, base_timeout, config).await;
+        res.attempts = attempt;
+        trace_attempt(attempt, &res);
+        // Only silence is worth retrying; every other outcome is an answer.
+        let answered = res.status != "filtered";
+        let merged = merge_attempt(best, res);
+        if answered || attempt > retries {
+            return merged;
+        }
+        best = Some(merged);
+        config.trace(|| format!("{}/{} retrying in {}ms", port, protocol, backoff.as_millis()));
         tokio::time::sleep(backoff).await;
         backoff *= 2;
+        attempt += 1;
     }
-    
-    scan_port_once(host, port, base_timeout).await
 }
 
-pub async fn scan_range(host: &str, start_port: u16, end_port: u16, tx: mpsc::Sender<ScanResult>) {
-    let concurrency = 256usize;
-    let timeout = Duration::from_secs(3);
-    let retries = 1u8;
-    let sem = Arc::new(Semaphore::new(concurrency));
-    let mut handles = Vec::with_capacity((end_port - start_port + 1) as usize);
+/// Largest number of addresses `parse_targets` expands a CIDR block to (an IPv4 /16).
+pub const MAX_TARGETS: u128 = 65_536;
 
-    for port in start_port..=end_port {
-        let host = host.to_string();
-        let tx = tx.clone();
-        let sem = sem.clone();
-        
-        let h = tokio::spawn(async move {
-            let permit = match sem.acquire().await {
-                Ok(p) => p,
-                Err(_) => return,
-            };
-            
-            let res = scan_with_retries(&host, port, timeout, retries).await;
-            let _ = tx.send(res).await;
-            drop(permit);
-        });
-        
-        handles.push(h);
+/// Strips the brackets of a URL-style IPv6 literal (`[::1]`); other input is returned as is.
+fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host)
+}
+
+/// Expands a target into addresses: a single IP yields itself and a CIDR block
+/// (`192.168.1.0/24`, `fd00::/120`) yields every address in it. IPv6 literals may
+/// be bracketed (`[::1]`, `[fd00::]/120`). Hostnames yield an empty list and are
+/// scanned by name, as are malformed blocks and blocks larger than `MAX_TARGETS`.
+pub fn parse_targets(host: &str) -> Vec<IpAddr> {
+    let host = host.trim();
+    if let Ok(ip) = unbracket(host).parse::<IpAddr>() {
+        return vec![ip];
     }
+    let Some((addr, prefix)) = host.split_once('/') else {
+        return Vec::new();
+    };
+    let (Ok(addr), Ok(prefix)) = (unbracket(addr).parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return Vec::new();
+    };
+    match addr {
+        IpAddr::V4(v4) if prefix <= 32 => {
+            let host_bits = 32 - prefix;
+            let size = 1u128 << host_bits;
+            if size > MAX_TARGETS {
+                return Vec::new();
+            }
+            let base = u32::from(v4) & u32::MAX.checked_shl(host_bits).unwrap_or(0);
+            (0..size as u32).map(|i| IpAddr::V4(Ipv4Addr::from(base + i))).collect()
+        }
+        IpAddr::V6(v6) if prefix <= 128 => {
+            let host_bits = 128 - prefix;
+            if host_bits > 16 {
+                return Vec::new();
+            }
+            let base = u128::from(v6) & u128::MAX.checked_shl(host_bits).unwrap_or(0);
+            (0..1u128 << host_bits).map(|i| IpAddr::V6(Ipv6Addr::from(base + i))).collect()
+        }
+        _ => Vec::new(),
+    }
+}
 
-    for h in handles {
-        let _ = h.await;
+/// Resolves every distinct address of `host` (A and AAAA), in resolver order.
+pub async fn resolve_all(host: &str) -> io::Result<Vec<IpAddr>> {
+    let mut ips = Vec::new();
+    for addr in tokio::net::lookup_host((unbracket(host.trim()), 0)).await? {
+        if !ips.contains(&addr.ip()) {
+            ips.push(addr.ip());
+        }
     }
+    Ok(ips)
+}
 
-    let _ = tx
-        .send(ScanResult {
-            port: 0,
-            status: "DONE".to_string(),
-            service: "".to_string(),
-            response_ms: 0,
-            banner: None,
-        })
-        .await;
+fn startup_delay(config: &ScanConfig, index: usize, concurrency: usize) -> Option<Duration> {
+    let max = config.start_jitter.filter(|_| index < concurrency)?;
+    Some(Duration::from_millis(rand::random_range(0..=max.as_millis() as u64)))
+}
+
+/// One address to probe. `name` goes into results (and HTTP `Host` headers); `addr`,
+/// when known, is connected to directly so the name is not looked up per port.
+struct Target {
+    name: String,
+    addr: Option<IpAddr>,
+}
+
+/// Expands `host` into targets, resolving a hostname once up front. Names are left
+/// unresolved for a custom connector or a proxy (they may resolve them themselves, e.g.
+/// through a tunnel) and for happy eyeballs, which needs every address of both families.
+async fn resolve_targets(host: &str, config: &ScanConfig) -> io::Result<Vec<Target>> {
+    let by_address = |ips: &[IpAddr]| ips.iter().map(|&ip| Target { name: ip.to_string(), addr: Some(ip) }).collect();
+    if !config.addresses.is_empty() {
+        return Ok(by_address(&config.addresses));
+    }
+    let ips = parse_targets(host);
+    if !ips.is_empty() {
+        return Ok(by_address(&ips));
+    }
+    if config.connector.is_some() || config.proxy.is_some() || config.happy_eyeballs {
+        return Ok(vec![Target { name: host.to_string(), addr: None }]);
+    }
+    let addr = tokio::net::lookup_host((host, 0))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address resolved"))?;
+    config.trace(|| format!("{} resolved to {}", host, addr.ip()));
+    Ok(vec![Target { name: host.to_string(), addr: Some(addr.ip()) }])
+}
+
+/// Runs `fut` unless `deadline` passes first; `None` means it did. Without a deadline
+/// `fut` always runs to completion.
+async fn before<F: std::future::Future>(deadline: Option<tokio::time::Instant>, fut: F) -> Option<F::Output> {
+    match deadline {
+        Some(at) => tokio::time::timeout_at(at, fut).await.ok(),
+        None => Some(fut.await),
+    }
 }
 
-pub async fn scan_top_ports(host: &str, tx: mpsc::Sender<ScanResult>) {
-    let concurrency = 128usize;
-    let timeout = Duration::from_secs(2);
+/// Pings every addressed target, at most `concurrency` at once, and keeps those that
+/// answer. Targets scanned by name are kept unpinged.
+async fn live_targets(targets: Vec<Arc<Target>>, timeout: Duration, concurrency: usize, config: &ScanConfig) -> Vec<Arc<Target>> {
+    let Some(method) = targets.iter().find_map(|t| t.addr).map(discovery::ping_method) else {
+        return targets;
+    };
     let sem = Arc::new(Semaphore::new(concurrency));
-    let mut handles = Vec::with_capacity(TOP_PORTS.len());
+    let mut pings = JoinSet::new();
+    for (i, target) in targets.iter().enumerate() {
+        let Some(addr) = target.addr else { continue };
+        let sem = sem.clone();
+        pings.spawn(async move {
+            let _permit = sem.acquire_owned().await;
+            (i, discovery::ping_with(method, addr, timeout).await)
+        });
+    }
+    let mut alive = vec![true; targets.len()];
+    let mut pinged = 0;
+    while let Some(Ok((i, up))) = pings.join_next().await {
+        alive[i] = up;
+        pinged += 1;
+    }
+    let live: Vec<Arc<Target>> = targets.into_iter().zip(alive).filter_map(|(t, up)| up.then_some(t)).collect();
+    config.trace(|| format!("Host discovery via {}: {} of {} hosts up", method, live.len(), pinged));
+    live
+}
 
-    for &port in TOP_PORTS {
-        let host = host.to_string();
-        let tx = tx.clone();
+/// Dispatches one task per (target, port, protocol) under a concurrency cap shared
+/// by every target and sends `ScanEvent::Finished` once every task has finished.
+/// `host` may be a CIDR block (see `parse_targets`). A hostname that fails to resolve
+/// yields a single `ScanEvent::Error` before `Finished`, and no probes.
+async fn run_scan(
+    host: &str,
+    ports: impl ExactSizeIterator<Item = u16>,
+    mode: ScanMode,
+    config: &ScanConfig,
+    tx: mpsc::Sender<ScanEvent>,
+) {
+    let started = Instant::now();
+    // Resolution, host discovery and the sweep all share this one budget.
+    let deadline = config.deadline.map(|d| tokio::time::Instant::now() + d);
+    let mut timed_out = false;
+    let concurrency = config.concurrency.unwrap_or(mode.concurrency).max(1);
+    let timeout = config.timeout.unwrap_or(mode.timeout);
+    let retries = config.retries.unwrap_or(mode.retries).min(MAX_RETRIES);
+    let ramp = config.ramp.filter(|r| r.step > 0 && r.step < concurrency);
+    let sem = Arc::new(Semaphore::new(ramp.map_or(concurrency, |r| r.step)));
+    // Helper tasks live in a JoinSet so they stop with the scan, even when the scan
+    // future itself is dropped (e.g. cancelled from the TUI).
+    let mut background = JoinSet::new();
+    if let Some(r) = ramp {
         let sem = sem.clone();
-        
-        let h = tokio::spawn(async move {
-            let permit = match sem.acquire().await {
-                Ok(p) => p,
-                Err(_) => return,
-            };
-            
-            let res = scan_with_retries(&host, port, timeout, 0).await;
-            let _ = tx.send(res).await;
-            drop(permit);
+        background.spawn(async move {
+            let mut allowed = r.step;
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + r.interval, r.interval);
+            while allowed < concurrency {
+                ticker.tick().await;
+                let add = r.step.min(concurrency - allowed);
+                sem.add_permits(add);
+                allowed += add;
+            }
+        });
+    }
+    let mut shared = config.clone();
+    shared.banner_timeout = Some(config.banner_timeout.unwrap_or(mode.banner_timeout));
+    let shared = Arc::new(shared);
+    let limiter = config.max_pps.filter(|&pps| pps > 0).map(|pps| Arc::new(RateLimiter::new(pps)));
+    let mut targets: Vec<Arc<Target>> = match before(deadline, resolve_targets(host, config)).await {
+        Some(Ok(targets)) => targets.into_iter().map(Arc::new).collect(),
+        Some(Err(e)) => {
+            let _ = tx.send(ScanEvent::Error(format!("cannot resolve {}: {}", host, e))).await;
+            Vec::new()
+        }
+        None => {
+            timed_out = true;
+            Vec::new()
+        }
+    };
+    let protocols = config.protocols().to_vec();
+    let mut ports: Vec<u16> = ports.collect();
+    if config.shuffle {
+        match config.seed {
+            Some(seed) => ports.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => ports.shuffle(&mut rand::rng()),
+        }
+    }
+    // Probes of skipped hosts count as done, so progress still reaches the end.
+    let mut skipped = 0;
+    if config.skip_dead_hosts && config.proxy.is_none() && !timed_out {
+        let pinged = targets.len();
+        match before(deadline, live_targets(targets, timeout, concurrency, config)).await {
+            Some(live) => {
+                targets = live;
+                skipped = (pinged - targets.len()) * ports.len() * protocols.len();
+                if let Some(probed) = &config.probed {
+                    probed.fetch_add(skipped, Ordering::Relaxed);
+                }
+            }
+            None => {
+                timed_out = true;
+                targets = Vec::new();
+            }
+        }
+    }
+    let total = ports.len() * targets.len() * protocols.len();
+    let completed = Arc::new(AtomicUsize::new(0));
+    let open = Arc::new(AtomicUsize::new(0));
+    let every = config.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL).max(Duration::from_millis(1));
+    {
+        let (completed, tx) = (completed.clone(), tx.clone());
+        background.spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            loop {
+                ticker.tick().await;
+                let done = skipped + completed.load(Ordering::Relaxed);
+                let _ = tx.try_send(ScanEvent::Progress { done, total: skipped + total });
+            }
+        });
+    }
+    if let Some(progress) = config.progress.clone() {
+        let (completed, open) = (completed.clone(), open.clone());
+        background.spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            loop {
+                ticker.tick().await;
+                let _ = progress.try_send(ProgressEvent::Progress {
+                    completed: completed.load(Ordering::Relaxed),
+                    total,
+                    open: open.load(Ordering::Relaxed),
+                });
+            }
         });
-        
-        handles.push(h);
     }
 
-    for h in handles {
-        let _ = h.await;
+    // Raised by the task sending the last wanted open result (`stop_after_open`).
+    let stop_after_open = config.stop_after_open.filter(|&n| n > 0);
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let sweep = async {
+        // A permit is taken before each task is spawned, so at most `concurrency` tasks
+        // exist at once however many hosts and ports the scan covers.
+        let mut tasks = JoinSet::new();
+        let mut i = 0;
+        'jobs: for port in ports {
+            for target in &targets {
+                for &protocol in &protocols {
+                    if config.cancelled() {
+                        break 'jobs;
+                    }
+                    let Ok(permit) = sem.clone().acquire_owned().await else {
+                        break 'jobs;
+                    };
+                    // Holding the permit while paused keeps every other job waiting too.
+                    while config.paused() && !config.cancelled() {
+                        tokio::time::sleep(PAUSE_POLL).await;
+                    }
+                    // Waiting for a permit can take a whole timeout; don't start work cancelled meanwhile.
+                    if config.cancelled() || stopped.load(Ordering::Relaxed) {
+                        break 'jobs;
+                    }
+                    while tasks.try_join_next().is_some() {}
+                    let target = target.clone();
+                    let tx = tx.clone();
+                    let delay = startup_delay(config, i, concurrency);
+                    let config = shared.clone();
+                    let (completed, open, stopped) = (completed.clone(), open.clone(), stopped.clone());
+                    let limiter = limiter.clone();
+                    i += 1;
+
+                    tasks.spawn(async move {
+                        if let Some(d) = delay {
+                            tokio::time::sleep(d).await;
+                            if config.cancelled() {
+                                return;
+                            }
+                        }
+
+                        let mut res = scan_with_retries(&target, port, protocol, timeout, retries, &config, limiter.as_deref()).await;
+                        res.software = res.banner.as_deref().and_then(|b| parse_version(&res.service, b));
+                        if let Some(probed) = &config.probed {
+                            probed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        let mut last_wanted = false;
+                        if res.status == "open" {
+                            let seen = open.fetch_add(1, Ordering::Relaxed) + 1;
+                            match stop_after_open {
+                                Some(n) if seen > n => return,
+                                Some(n) => last_wanted = seen == n,
+                                None => {}
+                            }
+                        }
+                        completed.fetch_add(1, Ordering::Relaxed);
+                        if !config.open_only || matches!(res.status.as_str(), "open" | "open|filtered") {
+                            let _ = tx.send(ScanEvent::Result(res)).await;
+                        } else if let Some(unsent) = &config.unsent {
+                            unsent.record(&res.status);
+                        }
+                        if last_wanted {
+                            stopped.store(true, Ordering::Relaxed);
+                        }
+                        drop(permit);
+                    });
+                }
+            }
+        }
+
+        // Probes still in flight when the last wanted open port was found are dropped,
+        // whether that happened before or after dispatch finished.
+        while !tasks.is_empty() {
+            if stopped.load(Ordering::Relaxed) {
+                tasks.abort_all();
+            }
+            tasks.join_next().await;
+        }
+    };
+    // On the deadline the sweep is dropped along with its JoinSet, which aborts every
+    // probe still in flight.
+    if !timed_out && before(deadline, sweep).await.is_none() {
+        timed_out = true;
+    }
+    background.abort_all();
+    if let Some(progress) = &config.progress {
+        let _ = progress
+            .send(ProgressEvent::Finished {
+                completed: completed.load(Ordering::Relaxed),
+                total,
+                open: open.load(Ordering::Relaxed),
+            })
+            .await;
+    }
+
+    let done = skipped + completed.load(Ordering::Relaxed);
+    let _ = tx.send(ScanEvent::Progress { done, total: skipped + total }).await;
+    let _ = tx.send(ScanEvent::Finished { elapsed: started.elapsed(), cancelled: config.cancelled(), timed_out }).await;
+}
+
+/// Scans `config.ports` on `config.host`. The other scan functions are shorthands for
+/// one `PortSelection` each.
+pub async fn scan(config: ScanConfig, tx: mpsc::Sender<ScanEvent>) {
+    let host = config.host.clone();
+    match config.ports.clone() {
+        PortSelection::Range(start, end) => scan_range(&host, start, end, &config, tx).await,
+        PortSelection::List(ports) => scan_ports(&host, ports, &config, tx).await,
+        PortSelection::Top => scan_top_ports(&host, &config, tx).await,
+        PortSelection::TopN(n) => run_scan(&host, top_ports(n).into_iter(), TOP_PORTS_MODE, &config, tx).await,
+        PortSelection::TopUdp => scan_top_udp_ports(&host, &config, tx).await,
     }
+}
 
-    let _ = tx
-        .send(ScanResult {
-            port: 0,
-            status: "DONE".to_string(),
-            service: "".to_string(),
-            response_ms: 0,
-            banner: None,
-        })
-        .await;
-}
\ No newline at end of file
+pub async fn scan_range(host: &str, start_port: u16, end_port: u16, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>) {
+    run_scan(host, start_port..=end_port, RANGE_MODE, config, tx).await;
+}
+
+/// Scans an explicit port list, e.g. one parsed by `ports::parse_port_spec`.
+/// Repeated ports are probed once so the counters match the ports scanned.
+pub async fn scan_ports(host: &str, mut ports: Vec<u16>, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>) {
+    ports.sort_unstable();
+    ports.dedup();
+    run_scan(host, ports.into_iter(), RANGE_MODE, config, tx).await;
+}
+
+/// Scans the whole top-ports ranking: `TOP_PORTS`, or the list installed with
+/// `ports::set_top_ports`.
+pub async fn scan_top_ports(host: &str, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>) {
+    run_scan(host, top_ports(usize::MAX).into_iter(), TOP_PORTS_MODE, config, tx).await;
+}
+
+/// Scans common UDP services (DNS, NTP, SNMP, ...) over UDP only, whatever
+/// `config.protocols` says.
+pub async fn scan_top_udp_ports(host: &str, config: &ScanConfig, tx: mpsc::Sender<ScanEvent>) {
+    let config = ScanConfig { protocols: vec![Protocol::Udp], ..config.clone() };
+    run_scan(host, TOP_UDP_PORTS.iter().copied(), TOP_PORTS_MODE, &config, tx).await;
+}
@@ -1,28 +1,112 @@
+//! Services module mapping port numbers to service names.
+//! A small built-in table covers common ports. A larger nmap-style `services` file
+//! can be loaded at startup and installed once; it is then consulted first, per protocol.
+//! # Structs
+//! - `Software` - A product and its version, as read from a banner.
+//! # Functions
+//! - `identify_service(port: u16) -> String` - Names the TCP service on a port.
+//! - `identify_service_for(port: u16, protocol: Protocol) -> String` - Names the service on a port for one protocol.
+//! - `load_services(path: &Path) -> io::Result<ServiceMap>` - Parses an nmap-style services file.
+//! - `set_services(map: ServiceMap) -> bool` - Installs a loaded map for the rest of the process.
+//! - `parse_version(service: &str, banner: &str) -> Option<Software>` - Extracts the product and version from a banner.
+//! # Examples
+//! ```no_run
+//! use night_tool::services::{identify_service, load_services, set_services};
+//! use std::path::Path;
+//!
+//! let map = load_services(Path::new("/usr/share/nmap/nmap-services")).unwrap();
+//! set_services(map);
+//! println!("{}", identify_service(8009));
+//! ```
+
+use crate::scanner::Protocol;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Service names keyed by port and protocol.
+pub type ServiceMap = HashMap<(u16, Protocol), String>;
+
+static SERVICES: OnceLock<ServiceMap> = OnceLock::new();
+
+/// Parses an nmap-style services file, where each line reads `name port/proto`
+/// followed by optional fields and `#` comments. Lines for protocols other than
+/// tcp and udp, and malformed lines, are skipped. The first name listed for a
+/// port and protocol wins.
+pub fn load_services(path: &Path) -> io::Result<ServiceMap> {
+    let text = std::fs::read_to_string(path)?;
+    let mut map = ServiceMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else { continue };
+        let Some((port, proto)) = port_proto.split_once('/') else { continue };
+        let protocol = match proto {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            _ => continue,
+        };
+        let Ok(port) = port.parse::<u16>() else { continue };
+        map.entry((port, protocol)).or_insert_with(|| name.to_string());
+    }
+    Ok(map)
+}
+
+/// Installs the loaded map. Only the first call takes effect; returns whether it did.
+pub fn set_services(map: ServiceMap) -> bool {
+    SERVICES.set(map).is_ok()
+}
+
+/// Looks a port up in the loaded map for `protocol`, falling back to the built-in table.
+pub fn identify_service_for(port: u16, protocol: Protocol) -> String {
+    match SERVICES.get().and_then(|m| m.get(&(port, protocol))) {
+        Some(name) => name.clone(),
+        None => builtin_service(port).to_string(),
+    }
+}
+
 ///this function identifies common services based on their port numbers.
 /// # Arguments
 /// * `port` - A u16 integer representing the port number. 
 /// # Returns
-/// A String representing the identified service name. 
+/// A String representing the identified service name. A loaded services file is
+/// consulted first (TCP entries only); unknown ports yield "unknown".
 /// # Examples
 /// ```
+/// use night_tool::services::identify_service;
 /// let service = identify_service(80);
 /// assert_eq!(service, "http");
 ///     
 /// ```
 pub fn identify_service(port: u16) -> String {
+    identify_service_for(port, Protocol::Tcp)
+}
+
+fn builtin_service(port: u16) -> &'static str {
     match port {
         21 => "ftp",
         22 => "ssh",
         23 => "telnet",
         25 => "smtp",
         53 => "dns",
+        67 => "dhcp",
+        69 => "tftp",
         80 => "http",
         110 => "pop3",
+        123 => "ntp",
+        137 => "netbios-ns",
         143 => "imap",
+        161 => "snmp",
         443 => "https",
         445 => "smb",
+        500 => "isakmp",
+        514 => "syslog",
+        1900 => "ssdp",
         3306 => "mysql",
         3389 => "rdp",
+        5353 => "mdns",
         5432 => "postgres",
         5900 => "vnc",
         8080 => "http-alt",
@@ -30,5 +114,73 @@ pub fn identify_service(port: u16) -> String {
         9200 => "elasticsearch",
         _ => "unknown",
     }
-    .to_string()
-}
\ No newline at end of file
+}
+
+/// Software named by a banner, kept as separate fields so versions can be compared
+/// across scans. Displays as `product version`, e.g. `OpenSSH 8.9p1`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Software {
+    pub product: String,
+    pub version: String,
+}
+
+impl Software {
+    fn new(product: &str, version: &str) -> Self {
+        Self { product: product.to_string(), version: version.to_string() }
+    }
+}
+
+impl std::fmt::Display for Software {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.product, self.version)
+    }
+}
+
+/// Extracts the software behind a banner, e.g. `OpenSSH` `8.9p1` from
+/// `SSH-2.0-OpenSSH_8.9p1 Ubuntu-3`, `nginx` `1.24.0` from an HTTP banner's
+/// `Server: nginx/1.24.0`, or `ProFTPD` `1.3.5` from `220 ProFTPD 1.3.5 Server`.
+/// Returns `None` unless both a product and a version are found.
+pub fn parse_version(service: &str, banner: &str) -> Option<Software> {
+    if let Some(ident) = banner.strip_prefix("SSH-") {
+        // SSH-protoversion-softwareversion comments
+        let software = ident.split_once('-')?.1.split_whitespace().next()?;
+        let (product, version) = software.split_once('_')?;
+        return is_version(version).then(|| Software::new(product, version));
+    }
+    if service.starts_with("http") || banner.starts_with("HTTP/") {
+        let server = banner.split(" | ").find_map(|part| part.strip_prefix("Server: "))?;
+        return product_version(server);
+    }
+    product_version(banner)
+}
+
+/// The first `Product/1.2` token, or `Product` followed by a `1.2` token. Brackets
+/// and punctuation around tokens are ignored.
+fn product_version(text: &str) -> Option<Software> {
+    let tokens: Vec<&str> = text
+        .split_whitespace()
+        .map(|t| t.trim_matches(|c: char| matches!(c, '(' | ')' | '[' | ']' | ',' | ';' | '"')))
+        .collect();
+    for (i, &token) in tokens.iter().enumerate() {
+        if let Some((product, version)) = token.split_once('/') {
+            if is_product(product) && is_version(version) {
+                return Some(Software::new(product, version));
+            }
+        } else if is_product(token) && let Some(&next) = tokens.get(i + 1) && is_version(next) {
+            return Some(Software::new(token, next));
+        }
+    }
+    None
+}
+
+fn is_product(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_alphabetic())
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Dotted and starting with a digit: `1.3.5`, `8.9p1`, `10.0`.
+fn is_version(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_digit())
+        && token.contains('.')
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+}
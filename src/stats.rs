@@ -0,0 +1,129 @@
+//! Stats module for summarising response times of a scan.
+//! Open ports measure the round trip to listening services; closed ports (answered
+//! with a reset) measure the network path itself, so both are kept apart.
+//! Timed-out probes carry no latency signal and are never counted.
+//! # Structs
+//! - `LatencyStats` - Percentiles and maximum over a set of response times.
+//! - `LatencySamples` - Response times collected per result state.
+//! - `RunningLatency` - Min, max and mean response time, updated as results arrive.
+//! # Functions
+//! - `response_histogram(results)` - Counts of open and closed results per `HISTOGRAM_BUCKETS` range.
+//! # Examples
+//! ```
+//! use night_tool::stats::LatencyStats;
+//! let stats = LatencyStats::from_samples([5, 1, 3, 2, 4]).unwrap();
+//! assert_eq!((stats.p50, stats.max), (3, 5));
+//! ```
+
+use crate::scanner::ScanResult;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub p50: u128,
+    pub p90: u128,
+    pub p99: u128,
+    pub max: u128,
+}
+
+impl LatencyStats {
+    /// Nearest-rank percentiles over the samples. `None` when there are none.
+    pub fn from_samples(samples: impl IntoIterator<Item = u128>) -> Option<Self> {
+        let mut ms: Vec<u128> = samples.into_iter().collect();
+        if ms.is_empty() {
+            return None;
+        }
+        ms.sort_unstable();
+        let rank = |p: usize| ms[(ms.len() * p).div_ceil(100).max(1) - 1];
+        Some(Self { count: ms.len(), p50: rank(50), p90: rank(90), p99: rank(99), max: ms[ms.len() - 1] })
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p50 {}ms, p90 {}ms, p99 {}ms, max {}ms (n={})", self.p50, self.p90, self.p99, self.max, self.count)
+    }
+}
+
+/// Response times of open and closed results, collected as results arrive.
+#[derive(Clone, Debug, Default)]
+pub struct LatencySamples {
+    open: Vec<u128>,
+    closed: Vec<u128>,
+}
+
+impl LatencySamples {
+    pub fn observe(&mut self, r: &ScanResult) {
+        match r.status.as_str() {
+            "open" => self.open.push(r.response_ms),
+            "closed" => self.closed.push(r.response_ms),
+            _ => {}
+        }
+    }
+
+    /// Summary lines for the open ports and, with `include_closed`, for every
+    /// answered port (open and closed together).
+    pub fn summary(&self, include_closed: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(s) = LatencyStats::from_samples(self.open.iter().copied()) {
+            lines.push(format!("Latency (open): {}", s));
+        }
+        if include_closed && let Some(s) = LatencyStats::from_samples(self.open.iter().chain(&self.closed).copied()) {
+            lines.push(format!("Latency (all answered): {}", s));
+        }
+        lines
+    }
+}
+
+impl<'a> FromIterator<&'a ScanResult> for LatencySamples {
+    fn from_iter<I: IntoIterator<Item = &'a ScanResult>>(iter: I) -> Self {
+        let mut samples = Self::default();
+        for r in iter {
+            samples.observe(r);
+        }
+        samples
+    }
+}
+
+/// Min, max and mean of response times, kept in constant space so it can be updated
+/// on every result of a large scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunningLatency {
+    count: u64,
+    sum: u128,
+    min: u128,
+    max: u128,
+}
+
+impl RunningLatency {
+    pub fn observe(&mut self, ms: u128) {
+        self.min = if self.count == 0 { ms } else { self.min.min(ms) };
+        self.max = self.max.max(ms);
+        self.sum += ms;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// (min, mean, max) in ms; `None` before the first sample.
+    pub fn min_avg_max(&self) -> Option<(u128, u128, u128)> {
+        (self.count > 0).then(|| (self.min, self.sum / u128::from(self.count), self.max))
+    }
+}
+
+/// Response-time ranges of `response_histogram`: a label and the exclusive upper bound in ms.
+pub const HISTOGRAM_BUCKETS: &[(&str, u128)] = &[("0-10", 10), ("10-50", 50), ("50-200", 200), ("200+", u128::MAX)];
+
+/// Counts open and closed results per `HISTOGRAM_BUCKETS` range, in bucket order.
+pub fn response_histogram<'a>(results: impl IntoIterator<Item = &'a ScanResult>) -> Vec<(&'static str, u64)> {
+    let mut counts: Vec<(&str, u64)> = HISTOGRAM_BUCKETS.iter().map(|&(label, _)| (label, 0)).collect();
+    for r in results.into_iter().filter(|r| matches!(r.status.as_str(), "open" | "closed")) {
+        if let Some(i) = HISTOGRAM_BUCKETS.iter().position(|&(_, below)| r.response_ms < below) {
+            counts[i].1 += 1;
+        }
+    }
+    counts
+}
@@ -0,0 +1,100 @@
+//! Throttle module for spotting targets that rate-limit or tarpit the scanner.
+//! The first results of a scan establish a baseline round-trip time and timeout ratio;
+//! later results are compared against it over a sliding window.
+//! # Structs
+//! - `ThrottleDetector` - Observes results and produces a one-time warning on sustained degradation.
+//! # Examples
+//! ```no_run
+//! use night_tool::throttle::ThrottleDetector;
+//! # let results: Vec<night_tool::scanner::ScanResult> = Vec::new();
+//! let mut detector = ThrottleDetector::default();
+//! for r in &results {
+//!     if let Some(warning) = detector.observe(r) {
+//!         eprintln!("{}", warning);
+//!     }
+//! }
+//! ```
+
+use crate::scanner::ScanResult;
+use std::collections::VecDeque;
+
+/// Results used to learn the baseline before any judgement is made.
+const BASELINE_SAMPLES: usize = 50;
+/// Size of the sliding window compared against the baseline.
+const WINDOW: usize = 50;
+/// A response counts as slow when it is this many times the baseline RTT...
+const SLOW_FACTOR: f64 = 4.0;
+/// ...and at least this many milliseconds above it, so tiny loopback RTTs don't trip it.
+const SLOW_MIN_EXTRA_MS: f64 = 100.0;
+/// Fraction of slow responses in the window that counts as a spike.
+const SLOW_RATIO: f64 = 0.6;
+/// Increase of the timeout ratio over the baseline that counts as degradation.
+const TIMEOUT_RATIO_JUMP: f64 = 0.4;
+
+#[derive(Debug, Default)]
+pub struct ThrottleDetector {
+    seen: usize,
+    baseline_rtt_sum: f64,
+    baseline_rtt_count: usize,
+    baseline_timeouts: usize,
+    /// (timed out, response_ms) for the most recent results after the baseline.
+    window: VecDeque<(bool, u128)>,
+    warned: bool,
+}
+
+impl ThrottleDetector {
+    /// Feeds one result. Returns a warning the first time sustained degradation is seen.
+    pub fn observe(&mut self, r: &ScanResult) -> Option<String> {
+        let timed_out = matches!(r.status.as_str(), "filtered" | "open|filtered");
+        self.seen += 1;
+
+        if self.seen <= BASELINE_SAMPLES {
+            if timed_out {
+                self.baseline_timeouts += 1;
+            } else {
+                self.baseline_rtt_sum += r.response_ms as f64;
+                self.baseline_rtt_count += 1;
+            }
+            return None;
+        }
+
+        if self.warned {
+            return None;
+        }
+
+        self.window.push_back((timed_out, r.response_ms));
+        if self.window.len() > WINDOW {
+            self.window.pop_front();
+        }
+        if self.window.len() < WINDOW {
+            return None;
+        }
+
+        let window_timeouts = self.window.iter().filter(|(t, _)| *t).count();
+        let baseline_timeout_ratio = self.baseline_timeouts as f64 / BASELINE_SAMPLES as f64;
+        let window_timeout_ratio = window_timeouts as f64 / WINDOW as f64;
+
+        let mut reason = None;
+        if window_timeout_ratio >= baseline_timeout_ratio + TIMEOUT_RATIO_JUMP {
+            reason = Some(format!(
+                "timeouts rose from {:.0}% to {:.0}%",
+                baseline_timeout_ratio * 100.0,
+                window_timeout_ratio * 100.0
+            ));
+        } else if self.baseline_rtt_count > 0 {
+            let baseline = self.baseline_rtt_sum / self.baseline_rtt_count as f64;
+            let threshold = (baseline * SLOW_FACTOR).max(baseline + SLOW_MIN_EXTRA_MS);
+            let answered: Vec<u128> = self.window.iter().filter(|(t, _)| !*t).map(|(_, ms)| *ms).collect();
+            let slow = answered.iter().filter(|&&ms| ms as f64 > threshold).count();
+            if !answered.is_empty() && slow as f64 / answered.len() as f64 >= SLOW_RATIO {
+                let avg = answered.iter().sum::<u128>() as f64 / answered.len() as f64;
+                reason = Some(format!("response time rose from {:.0}ms to {:.0}ms", baseline, avg));
+            }
+        }
+
+        reason.map(|why| {
+            self.warned = true;
+            format!("Target may be rate-limiting the scan ({}); consider lowering concurrency", why)
+        })
+    }
+}
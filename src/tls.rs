@@ -0,0 +1,248 @@
+//! TLS module for reading the certificate of an HTTPS service without a TLS library.
+//! The scanner offers TLS 1.2 only, so the server sends its certificate in the clear
+//! right after the ServerHello; the handshake is abandoned as soon as it arrives.
+//! Servers that insist on TLS 1.3 answer with an alert and yield no summary.
+//! # Structs
+//! - `CertSummary` - Subject common name and expiry date of a certificate.
+//! # Functions
+//! - `inspect_certificate(stream, server_name, timeout)` - Starts a handshake and summarizes the server's certificate.
+//! - `parse_certificate(der: &[u8]) -> Option<CertSummary>` - Summarizes a DER-encoded X.509 certificate.
+//! # Examples
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! use night_tool::tls::inspect_certificate;
+//! use std::time::Duration;
+//!
+//! let mut stream = tokio::net::TcpStream::connect(("example.com", 443)).await?;
+//! if let Some(cert) = inspect_certificate(&mut stream, Some("example.com"), Duration::from_secs(2)).await {
+//!     println!("{}", cert); // CN=example.com exp=2025-06-01
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Most handshake bytes read while waiting for the Certificate message.
+const MAX_HANDSHAKE_BYTES: usize = 64 * 1024;
+
+const RECORD_HANDSHAKE: u8 = 22;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+
+/// ECDHE and plain RSA suites with AES-GCM, ChaCha20 and AES-CBC: enough for any
+/// TLS 1.2 server to pick one.
+const CIPHER_SUITES: &[u16] = &[
+    0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc009, 0xc013, 0xc00a, 0xc014, 0x009c, 0x009d, 0x002f, 0x0035,
+];
+
+/// x25519, secp256r1, secp384r1.
+const GROUPS: &[u16] = &[0x001d, 0x0017, 0x0018];
+
+/// ECDSA and RSA (PKCS#1 and PSS) with SHA-256/384/512, plus RSA with SHA-1.
+const SIGNATURE_ALGORITHMS: &[u16] = &[0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601, 0x0201];
+
+/// What the banner shows for a TLS service, e.g. `CN=example.com exp=2025-06-01`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertSummary {
+    /// Subject CN. Certificates naming their hosts only in subjectAltName have none.
+    pub common_name: Option<String>,
+    /// Last day of validity, as `YYYY-MM-DD` (UTC).
+    pub not_after: Option<String>,
+}
+
+impl fmt::Display for CertSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(cn) = &self.common_name {
+            parts.push(format!("CN={}", cn));
+        }
+        if let Some(exp) = &self.not_after {
+            parts.push(format!("exp={}", exp));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Sends a TLS 1.2 ClientHello and summarizes the first certificate the server
+/// returns. `server_name` goes into SNI; pass `None` for IP targets. Returns `None`
+/// on any failure, including servers that only speak TLS 1.3.
+pub async fn inspect_certificate<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    server_name: Option<&str>,
+    timeout: Duration,
+) -> Option<CertSummary> {
+    let exchange = async {
+        stream.write_all(&client_hello(server_name)).await.ok()?;
+        let der = read_certificate(stream).await?;
+        parse_certificate(&der)
+    };
+    tokio::time::timeout(timeout, exchange).await.ok().flatten()
+}
+
+fn client_hello(server_name: Option<&str>) -> Vec<u8> {
+    let mut extensions = Vec::new();
+    if let Some(name) = server_name {
+        let name = name.as_bytes();
+        let mut list = vec![0u8];
+        put_u16_prefixed(&mut list, name);
+        let mut sni = Vec::new();
+        put_u16_prefixed(&mut sni, &list);
+        put_extension(&mut extensions, 0x0000, &sni);
+    }
+    put_extension(&mut extensions, 0x000a, &u16_list(GROUPS));
+    // ec_point_formats: uncompressed only.
+    put_extension(&mut extensions, 0x000b, &[1, 0]);
+    put_extension(&mut extensions, 0x000d, &u16_list(SIGNATURE_ALGORITHMS));
+
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&rand::random::<[u8; 32]>());
+    // No session ID; null compression only.
+    body.push(0);
+    body.extend_from_slice(&u16_list(CIPHER_SUITES));
+    body.extend_from_slice(&[1, 0]);
+    put_u16_prefixed(&mut body, &extensions);
+
+    let mut handshake = vec![1];
+    put_u24_prefixed(&mut handshake, &body);
+    let mut record = vec![RECORD_HANDSHAKE, 0x03, 0x01];
+    put_u16_prefixed(&mut record, &handshake);
+    record
+}
+
+fn put_u16_prefixed(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn put_u24_prefixed(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(data);
+}
+
+fn put_extension(out: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    out.extend_from_slice(&kind.to_be_bytes());
+    put_u16_prefixed(out, data);
+}
+
+/// A u16-length-prefixed list of u16 values.
+fn u16_list(values: &[u16]) -> Vec<u8> {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+    let mut out = Vec::new();
+    put_u16_prefixed(&mut out, &bytes);
+    out
+}
+
+/// Reads handshake records until the Certificate message is complete and returns its
+/// first (leaf) certificate. Alerts, other record types and oversized handshakes end it.
+async fn read_certificate<S: AsyncRead + Unpin>(stream: &mut S) -> Option<Vec<u8>> {
+    let mut handshake = Vec::new();
+    loop {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).await.ok()?;
+        if header[0] != RECORD_HANDSHAKE {
+            return None;
+        }
+        let len = usize::from(u16::from_be_bytes([header[3], header[4]]));
+        if handshake.len() + len > MAX_HANDSHAKE_BYTES {
+            return None;
+        }
+        let start = handshake.len();
+        handshake.resize(start + len, 0);
+        stream.read_exact(&mut handshake[start..]).await.ok()?;
+
+        // Walk the complete messages received so far.
+        let mut rest = handshake.as_slice();
+        while rest.len() >= 4 {
+            let len = u24(&rest[1..4]);
+            let Some(body) = rest.get(4..4 + len) else { break };
+            if rest[0] == HANDSHAKE_CERTIFICATE {
+                // certificate_list<u24> of certificate<u24>; the leaf comes first.
+                let first = body.get(3..6).map(u24)?;
+                return body.get(6..6 + first).map(<[u8]>::to_vec);
+            }
+            rest = &rest[4 + len..];
+        }
+    }
+}
+
+fn u24(b: &[u8]) -> usize {
+    (usize::from(b[0]) << 16) | (usize::from(b[1]) << 8) | usize::from(b[2])
+}
+
+/// Splits one DER element off `buf`: (tag, contents, remainder).
+fn der_element(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = buf.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first & 0x80 == 0 {
+        (usize::from(first), rest)
+    } else {
+        let n = usize::from(first & 0x7f);
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |acc, &b| (acc << 8) | usize::from(b));
+        (len, &rest[n..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Summarizes a DER-encoded X.509 certificate. Returns `None` if it doesn't parse.
+pub fn parse_certificate(der: &[u8]) -> Option<CertSummary> {
+    const SEQUENCE: u8 = 0x30;
+    let (SEQUENCE, cert, _) = der_element(der)? else { return None };
+    let (SEQUENCE, tbs, _) = der_element(cert)? else { return None };
+    let mut fields = tbs;
+    // Skip the optional [0] version, then serial number, signature algorithm and issuer.
+    if fields.first() == Some(&0xa0) {
+        fields = der_element(fields)?.2;
+    }
+    for _ in 0..3 {
+        fields = der_element(fields)?.2;
+    }
+    let (SEQUENCE, validity, rest) = der_element(fields)? else { return None };
+    let (_, _, validity) = der_element(validity)?;
+    let (time_tag, not_after, _) = der_element(validity)?;
+    let (SEQUENCE, subject, _) = der_element(rest)? else { return None };
+    Some(CertSummary { common_name: common_name(subject), not_after: der_date(time_tag, not_after) })
+}
+
+/// Value of the last CN attribute in an X.501 Name.
+fn common_name(mut name: &[u8]) -> Option<String> {
+    const CN_OID: &[u8] = &[0x55, 0x04, 0x03];
+    let mut cn = None;
+    while let Some((_, set, rest)) = der_element(name) {
+        let mut attrs = set;
+        while let Some((_, attr, more)) = der_element(attrs) {
+            if let Some((0x06, oid, value)) = der_element(attr)
+                && oid == CN_OID
+                && let Some((tag, text, _)) = der_element(value)
+                // UTF8String, PrintableString, TeletexString, IA5String.
+                && matches!(tag, 0x0c | 0x13 | 0x14 | 0x16)
+            {
+                cn = Some(String::from_utf8_lossy(text).into_owned());
+            }
+            attrs = more;
+        }
+        name = rest;
+    }
+    cn
+}
+
+/// Formats a UTCTime (`YYMMDD...`) or GeneralizedTime (`YYYYMMDD...`) as `YYYY-MM-DD`.
+fn der_date(tag: u8, value: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(value).ok()?;
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let (year, rest) = match tag {
+        0x17 => {
+            let yy: u32 = text.get(..2).filter(|s| digits(s))?.parse().ok()?;
+            // RFC 5280: UTCTime years 50-99 are 19xx, the rest 20xx.
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, text.get(2..)?)
+        }
+        0x18 => (text.get(..4).filter(|s| digits(s))?.parse().ok()?, text.get(4..)?),
+        _ => return None,
+    };
+    let (month, day) = (rest.get(..2).filter(|s| digits(s))?, rest.get(2..4).filter(|s| digits(s))?);
+    Some(format!("{:04}-{}-{}", year, month, day))
+}
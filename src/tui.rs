@@ -0,0 +1,712 @@
+//! Event loop for the interactive TUI, decoupled from terminal construction.
+//! `run_app` works with any Ratatui `Backend`, so the binary drives it over
+//! `CrosstermBackend` while tests can render through `TestBackend`.
+//! # Functions
+//! - `run_app(terminal, app, scan_config, tx, trace_rx)` - Runs the key/draw loop until the user quits.
+//! # Examples
+//! ```no_run
+//! use night_tool::{scanner::{ScanConfig, ScanEvent}, tui, ui::App};
+//! use ratatui::{backend::TestBackend, Terminal};
+//! use tokio::sync::mpsc;
+//!
+//! # async fn demo() -> std::io::Result<()> {
+//! let mut terminal = Terminal::new(TestBackend::new(120, 40))?;
+//! let (tx, rx) = mpsc::channel::<ScanEvent>(2048);
+//! let mut app = App::new(rx);
+//! tui::run_app(&mut terminal, &mut app, ScanConfig::default(), tx, None).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cli;
+use crate::config;
+use crate::export;
+use crate::ports::{self, format_port_spec, parse_port_spec};
+use crate::scanner::{self, PortSelection, ScanConfig, ScanEvent};
+use crate::stats::LatencySamples;
+use crate::ui::{self, App};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::backend::Backend;
+use ratatui::layout::{Position, Rect};
+use ratatui::Terminal;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+/// Share of timed-out ports in a finished scan above which a rescan with a
+/// longer timeout is suggested.
+const RETRY_TIMEOUT_RATIO: f64 = 0.3;
+
+/// Log events moved by PageUp / PageDown.
+const LOG_PAGE: isize = 10;
+
+/// The most recent scan, with the connect timeout it ran with.
+struct LastScan {
+    host: String,
+    kind: PortSelection,
+    timeout: Duration,
+}
+
+/// Starts a scan task for `host` with the active profile applied and marks the
+/// app as scanning. `timeout` overrides the configured connect timeout. In
+/// all-addresses mode the host is resolved first; `None` means that failed.
+async fn launch_scan(
+    app: &mut App,
+    scan_config: &ScanConfig,
+    tx: &mpsc::Sender<ScanEvent>,
+    host: &str,
+    kind: PortSelection,
+    timeout: Option<Duration>,
+) -> Option<(tokio::task::JoinHandle<()>, LastScan)> {
+    let mut config = scan_config.clone();
+    // The Proxy field starts out holding `--proxy`, so it alone decides.
+    match app.proxy() {
+        Ok(proxy) => config.proxy = proxy,
+        Err(e) => {
+            app.push_log(e);
+            return None;
+        }
+    }
+    let cidr = host.contains('/');
+    if cidr && scanner::parse_targets(host).is_empty() {
+        app.push_log(format!("Invalid CIDR block {} (largest allowed is a /16)", host));
+        return None;
+    }
+    if app.all_addresses && !cidr {
+        match scanner::resolve_all(host).await {
+            Ok(ips) => {
+                app.push_log(format!("{} -> {}", host, cli::join_ips(&ips)));
+                config.addresses = ips;
+            }
+            Err(e) => {
+                app.push_log(format!("Cannot resolve {}: {}", host, e));
+                return None;
+            }
+        }
+    }
+    app.begin_scan(host);
+    if let Some(path) = app.live_path.clone() {
+        match export::LiveJsonl::open(&path) {
+            Ok(w) => {
+                app.live_writer = Some(w);
+                app.push_log(format!("Streaming results to {}", path.display()));
+            }
+            Err(e) => app.push_log(format!("Cannot stream results to {}: {}", path.display(), e)),
+        }
+    }
+    let mut scope = match &kind {
+        PortSelection::Range(start, end) => format!("ports {}-{}", start, end),
+        PortSelection::List(ports) => format!("ports {}", format_port_spec(ports)),
+        PortSelection::Top => "top ports".to_string(),
+        PortSelection::TopN(_) => format!("top {} ports", kind.port_count()),
+        PortSelection::TopUdp => "top UDP ports".to_string(),
+    };
+    if !config.addresses.is_empty() {
+        scope.push_str(&format!(" on {}", cli::join_ips(&config.addresses)));
+    }
+    app.start_job(host, scope);
+    let unsent = Arc::new(scanner::UnsentCounts::default());
+    config.unsent = Some(unsent.clone());
+    app.unsent = Some(unsent);
+    let cancel = Arc::new(AtomicBool::new(false));
+    config.cancel = Some(cancel.clone());
+    app.cancel = Some(cancel);
+    let pause = Arc::new(AtomicBool::new(false));
+    config.pause = Some(pause.clone());
+    app.pause = Some(pause);
+    if let Some(p) = app.active_profile() {
+        p.apply_to(&mut config);
+    }
+    config.shuffle = app.shuffle;
+    // `--max-banner-bytes 0` only sets the initial state of the toggle.
+    if !app.grab_banners {
+        config.max_banner_bytes = Some(0);
+    } else if config.max_banner_bytes == Some(0) {
+        config.max_banner_bytes = None;
+    }
+    if let Some(proxy) = config.proxy {
+        app.push_log(format!("Connecting through SOCKS5 proxy {}", proxy));
+    }
+    if let Some(method) = cli::ping_method_for(host, &config) {
+        app.push_log(format!("Skipping hosts that don't answer a {} ping", method));
+    }
+    let ports = kind.port_count();
+    let targets = match config.addresses.len() {
+        0 => scanner::parse_targets(host).len().max(1),
+        n => n,
+    };
+    let protocols = match kind {
+        PortSelection::TopUdp => 1,
+        _ => config.protocols.len().max(1),
+    };
+    app.total_ports = ports * targets * protocols;
+    // Values typed into the top bar win over flags and the profile; an F12 retry
+    // timeout wins over everything.
+    let (concurrency, ui_timeout, max_pps) = app.tuning();
+    if concurrency.is_some() {
+        config.concurrency = concurrency;
+    }
+    if max_pps.is_some() {
+        config.max_pps = max_pps;
+    }
+    if ui_timeout.is_some() {
+        config.timeout = ui_timeout;
+    }
+    if let Some(retries) = app.retries() {
+        config.retries = Some(retries);
+    }
+    if timeout.is_some() {
+        config.timeout = timeout;
+    }
+    let last = LastScan { host: host.to_string(), kind: kind.clone(), timeout: config.timeout.unwrap_or(kind.default_timeout()) };
+
+    config.host = host.to_string();
+    config.ports = kind;
+    let handle = tokio::spawn(scanner::scan(config, tx.clone()));
+
+    app.scan_started_wall = Some(SystemTime::now());
+    app.scan_finished_wall = None;
+    app.is_scanning = true;
+    Some((handle, last))
+}
+
+/// Reads one terminal event. Mouse events are handled here; key events are returned.
+fn read_key<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<Option<KeyEvent>> {
+    match event::read()? {
+        Event::Key(key) => Ok(Some(key)),
+        Event::Mouse(mouse) => {
+            // Prompts and popups only take keys.
+            if !app.show_help && app.last_summary.is_none() && app.note_input.is_none() && app.goto_input.is_none() && !app.filter_editing {
+                let table = ui::results_area(terminal.get_frame().area(), app);
+                handle_mouse(app, mouse, table);
+            }
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A click in the Results table selects the row under it; the wheel moves the selection.
+fn handle_mouse(app: &mut App, mouse: MouseEvent, table: Rect) {
+    let inside = table.contains(Position::new(mouse.column, mouse.row));
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if inside => {
+            app.click_row(table, mouse.row);
+        }
+        MouseEventKind::ScrollDown if inside => app.move_selection(1),
+        MouseEventKind::ScrollUp if inside => app.move_selection(-1),
+        _ => {}
+    }
+}
+
+pub async fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    scan_config: ScanConfig,
+    tx: mpsc::Sender<ScanEvent>,
+    mut trace_rx: Option<mpsc::Receiver<String>>,
+) -> io::Result<()> {
+    let tick_rate = std::time::Duration::from_millis(80);
+    let mut last_tick = Instant::now();
+    let mut scan_task: Option<tokio::task::JoinHandle<()>> = None;
+    let mut last_scan: Option<LastScan> = None;
+    // Set when the last scan finished with many timeouts; F12 relaunches it with this timeout.
+    let mut retry_timeout: Option<Duration> = None;
+    // Kept alive for the session: on X11 the copied text vanishes when the handle drops.
+    let mut clipboard: Option<arboard::Clipboard> = None;
+
+    loop {
+        terminal.draw(|f| ui::draw(f, app))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| std::time::Duration::from_secs(0));
+
+        if crossterm::event::poll(timeout)? && let Some(key) = read_key(terminal, app)? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            // Raw mode turns Ctrl-C into a key press instead of SIGINT; quit on it from
+            // anywhere, prompts included.
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                if let Some(handle) = scan_task.take() {
+                    handle.abort();
+                    app.finish_job("aborted");
+                }
+                break;
+            }
+
+            if app.show_help {
+                app.show_help = false;
+                continue;
+            }
+
+            if app.last_summary.take().is_some() {
+                continue;
+            }
+
+            if let Some((_, text)) = app.note_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => text.push(c),
+                    KeyCode::Backspace => { text.pop(); }
+                    KeyCode::Enter => {
+                        if let Some(port) = app.commit_note() {
+                            app.push_log(format!("Note saved for port {}", port));
+                        }
+                    }
+                    KeyCode::Esc => app.note_input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if app.filter_editing {
+                // The table narrows as the search text is typed.
+                match key.code {
+                    KeyCode::Char(c) => app.set_filter(format!("{}{}", app.filter_input, c)),
+                    KeyCode::Backspace => {
+                        let mut text = app.filter_input.clone();
+                        text.pop();
+                        app.set_filter(text);
+                    }
+                    KeyCode::Up => app.set_filter(app.last_query.clone()),
+                    KeyCode::Enter => app.confirm_filter(),
+                    KeyCode::Esc => app.reset_filter(),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(text) = app.goto_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() => text.push(c),
+                    KeyCode::Backspace => { text.pop(); }
+                    KeyCode::Enter => {
+                        if let Some(msg) = app.commit_goto() {
+                            app.push_log(msg);
+                        }
+                    }
+                    KeyCode::Esc => app.goto_input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.goto_input = Some(String::new());
+                }
+
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.filter_editing = true;
+                }
+
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let Some(profile) = app.profile_from_inputs() else {
+                        app.push_log("Enter a target before saving a profile".to_string());
+                        continue;
+                    };
+                    let path = app.profiles_path.clone();
+                    match config::save_profile(&path, &profile) {
+                        Ok(()) => {
+                            app.push_log(format!("Saved profile {} to {}", profile.name, path.display()));
+                            app.add_profile(profile);
+                        }
+                        Err(e) => app.push_log(format!("Saving profile failed: {}", e)),
+                    }
+                }
+
+                KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let path = PathBuf::from(format!("audit_{}.log", export::timestamp_slug(SystemTime::now())));
+                    let written = std::fs::File::create(&path)
+                        .and_then(|f| export::write_audit_log(app, std::io::BufWriter::new(f)));
+                    match written {
+                        Ok(()) => app.push_log(format!("Wrote audit log to {}", path.display())),
+                        Err(e) => app.push_log(format!("Audit log failed: {}", e)),
+                    }
+                }
+
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.show_histogram = !app.show_histogram;
+                }
+
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let next = ui::TOP_COUNT_CHOICES.iter().position(|&n| n == app.top_count).map_or(0, |i| i + 1);
+                    app.top_count = ui::TOP_COUNT_CHOICES[next % ui::TOP_COUNT_CHOICES.len()];
+                    let ranked = ports::top_ports(usize::MAX).len();
+                    app.push_log(format!("T scans the top {} ports ({} ranked)", app.top_count.min(ranked), ranked));
+                }
+
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.grab_banners = !app.grab_banners;
+                    let state = if app.grab_banners { "on" } else { "off" };
+                    app.push_log(format!("Banner grabbing for the next scan: {}", state));
+                }
+
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    if let Some(handle) = scan_task.take() {
+                        handle.abort();
+                        app.finish_job("aborted");
+                    }
+                    break;
+                }
+
+                KeyCode::Char('s') | KeyCode::Enter => {
+                    let target_host = app.host_input.trim().to_string();
+
+                    if target_host.is_empty() {
+                        app.push_log("Host is empty. Enter IP or domain.".to_string());
+                        continue;
+                    }
+
+                    if scan_task.is_some() {
+                        app.push_log("Scan already running".to_string());
+                        continue;
+                    }
+
+                    if !app.ports_valid() {
+                        app.push_log("Invalid port range (fix the fields shown in red)".to_string());
+                        continue;
+                    }
+
+                    // A comma or dash in the End field makes it a port list such as
+                    // `22,80,443` or `20-25,8080`; the Start field is then ignored.
+                    let end_input = app.end_port_input.trim();
+                    let (kind, scope) = if end_input.contains([',', '-']) {
+                        match parse_port_spec(end_input) {
+                            Ok(ports) => {
+                                let scope = format_port_spec(&ports);
+                                (PortSelection::List(ports), scope)
+                            }
+                            Err(e) => {
+                                app.push_log(format!("Invalid port list: {}", e));
+                                continue;
+                            }
+                        }
+                    } else {
+                        let start_port = app.start_port_input.parse::<u16>().unwrap_or(1);
+                        let end_port = end_input.parse::<u16>().unwrap_or(65535);
+                        if start_port == 0 || end_port == 0 || start_port > end_port {
+                            app.push_log("Invalid port range".to_string());
+                            continue;
+                        }
+                        (PortSelection::Range(start_port, end_port), format!("{}-{}", start_port, end_port))
+                    };
+
+                    let Some((handle, last)) = launch_scan(app, &scan_config, &tx, &target_host, kind, None).await else {
+                        continue;
+                    };
+                    scan_task = Some(handle);
+                    last_scan = Some(last);
+                    retry_timeout = None;
+                    app.push_log(format!("Scan started: {}:{}", target_host, scope));
+                }
+
+                KeyCode::Char(letter @ ('t' | 'u')) if letter == 't' || key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let target_host = app.host_input.trim().to_string();
+
+                    if target_host.is_empty() {
+                        app.push_log("Host is empty. Enter IP or domain.".to_string());
+                        continue;
+                    }
+
+                    if scan_task.is_some() {
+                        app.push_log("Scan already running".to_string());
+                        continue;
+                    }
+
+                    let (kind, label) = if letter == 'u' { (PortSelection::TopUdp, "Top UDP ports") } else { (PortSelection::TopN(app.top_count), "Top ports") };
+                    let Some((handle, last)) = launch_scan(app, &scan_config, &tx, &target_host, kind, None).await else {
+                        continue;
+                    };
+                    scan_task = Some(handle);
+                    last_scan = Some(last);
+                    retry_timeout = None;
+                    app.push_log(format!("{} scan started for {}", label, target_host));
+                }
+
+                KeyCode::Char('c') => {
+                    if scan_task.is_none() {
+                        app.push_log("No active scan".to_string());
+                        continue;
+                    }
+                    // The first press lets probes in flight finish; the scan then ends with
+                    // a CANCELLED sentinel. A second press aborts at once.
+                    let cancel = app.cancel.as_ref().filter(|c| !c.swap(true, Ordering::Relaxed));
+                    if cancel.is_some() {
+                        app.push_log("Cancelling scan; waiting for probes in flight (press C again to abort)".to_string());
+                    } else if let Some(handle) = scan_task.take() {
+                        handle.abort();
+                        app.finish_job("aborted");
+                        app.is_scanning = false;
+                        let elapsed = app.scan_elapsed().unwrap_or_default();
+                        app.push_log(format!("Scan aborted ({}s)", elapsed.as_secs()));
+                    }
+                }
+
+                KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => app.clear_log(),
+
+                KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => app.cycle_sort(),
+
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => app.sort_desc = !app.sort_desc,
+
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => match app.toggle_pause() {
+                    Some(true) => app.push_log("Scan paused; probes in flight finish (press Ctrl+P to resume)".to_string()),
+                    Some(false) => app.push_log("Scan resumed".to_string()),
+                    None => app.push_log("No scan running".to_string()),
+                },
+
+                KeyCode::F(12) => {
+                    if scan_task.is_some() {
+                        app.push_log("Scan already running".to_string());
+                        continue;
+                    }
+                    let (Some(timeout), Some(last)) = (retry_timeout.take(), last_scan.as_ref()) else {
+                        app.push_log("No timeout retry suggested".to_string());
+                        continue;
+                    };
+                    let (host, kind) = (last.host.clone(), last.kind.clone());
+                    let Some((handle, last)) = launch_scan(app, &scan_config, &tx, &host, kind, Some(timeout)).await else {
+                        continue;
+                    };
+                    scan_task = Some(handle);
+                    last_scan = Some(last);
+                    app.push_log(format!("Rescan of {} started with {}ms timeout", host, timeout.as_millis()));
+                }
+
+                KeyCode::F(1) => app.show_help = true,
+
+                KeyCode::F(2) => app.toggle_coalesce_log(),
+
+                KeyCode::F(3) => {
+                    let editing = app.begin_note();
+                    if !editing {
+                        app.push_log("No result to annotate".to_string());
+                    }
+                }
+
+                KeyCode::F(4) => {
+                    app.compact = !app.compact;
+                    let layout = if app.compact { "compact" } else { "detailed" };
+                    app.push_log(format!("Table layout: {}", layout));
+                }
+
+                KeyCode::F(11) => {
+                    let forward = !key.modifiers.contains(KeyModifiers::SHIFT);
+                    if app.last_query.is_empty() {
+                        app.push_log("No search to repeat; press Ctrl+F to search".to_string());
+                    } else if !app.jump_to_match(forward) {
+                        app.push_log(format!("No shown result matches \"{}\"", app.last_query));
+                    }
+                }
+
+                KeyCode::F(10) => {
+                    app.group_by_service = !app.group_by_service;
+                    let view = if app.group_by_service { "by service" } else { "by port" };
+                    app.push_log(format!("Results view: {}", view));
+                }
+
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.shuffle = !app.shuffle;
+                    let order = if app.shuffle { "random" } else { "ascending" };
+                    app.push_log(format!("Port order for the next scan: {}", order));
+                }
+
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.toggle_show_only_open();
+                    let view = if app.show_only_open { "open ports only" } else { "all ports" };
+                    app.push_log(format!("Results table: {}", view));
+                }
+
+                KeyCode::F(5) => {
+                    if app.results.is_empty() {
+                        app.push_log("No results to export".to_string());
+                        continue;
+                    }
+                    let now = SystemTime::now();
+                    let started = app.scan_started_wall.unwrap_or(now);
+                    let finished = app.scan_finished_wall.unwrap_or(now);
+                    let path = PathBuf::from(format!("scan_{}.xml", export::timestamp_slug(now)));
+                    match export::export_nmap_xml(&app.results, &app.scan_host, started, finished, &path) {
+                        Ok(()) => app.push_log(format!("Exported nmap XML to {}", path.display())),
+                        Err(e) => app.push_log(format!("XML export failed: {}", e)),
+                    }
+                }
+
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if app.results.is_empty() {
+                        app.push_log("No results to export".to_string());
+                        continue;
+                    }
+                    let path = PathBuf::from(format!("scan_{}.json", export::timestamp_slug(SystemTime::now())));
+                    match export::export_json(&app.results, &app.notes, &path) {
+                        Ok(()) => app.push_log(format!("Exported JSON to {}", path.display())),
+                        Err(e) => app.push_log(format!("JSON export failed: {}", e)),
+                    }
+                }
+
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if app.results.is_empty() {
+                        app.push_log("No results to export".to_string());
+                        continue;
+                    }
+                    let path = PathBuf::from(format!("scan_{}.csv", export::timestamp_slug(SystemTime::now())));
+                    match export::export_csv(&app.results, &app.notes, &path) {
+                        Ok(()) => app.push_log(format!("Exported CSV to {}", path.display())),
+                        Err(e) => app.push_log(format!("CSV export failed: {}", e)),
+                    }
+                }
+
+                KeyCode::F(7) => {
+                    let mut tsv = Vec::new();
+                    if clipboard.is_none() {
+                        clipboard = arboard::Clipboard::new().map_err(|e| app.push_log(format!("Clipboard unavailable: {}", e))).ok();
+                    }
+                    let Some(cb) = clipboard.as_mut() else { continue };
+                    let copied = export::write_tsv(&app.results, &app.notes, &mut tsv)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| cb.set_text(String::from_utf8_lossy(&tsv)).map_err(|e| e.to_string()));
+                    match copied {
+                        Ok(()) => app.push_log(format!("Copied {} rows to clipboard", app.results.len())),
+                        Err(e) => app.push_log(format!("Clipboard copy failed: {}", e)),
+                    }
+                }
+
+                KeyCode::F(8) => {
+                    let path = PathBuf::from(format!("report_{}.txt", export::timestamp_slug(SystemTime::now())));
+                    let written = std::fs::File::create(&path)
+                        .and_then(|f| export::write_report(app, std::io::BufWriter::new(f)));
+                    match written {
+                        Ok(()) => app.push_log(format!("Wrote report to {}", path.display())),
+                        Err(e) => app.push_log(format!("Report failed: {}", e)),
+                    }
+                }
+
+                KeyCode::F(9) => {
+                    match app.next_profile().map(|p| p.name.clone()) {
+                        Some(name) => app.push_log(format!("Profile: {}", name)),
+                        None => app.push_log("No profiles in config".to_string()),
+                    }
+                }
+
+                KeyCode::F(6) => {
+                    app.clear_cache();
+                    app.push_log("Result cache cleared".to_string());
+                }
+
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => app.scroll_log(1),
+
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => app.scroll_log(-1),
+
+                KeyCode::PageUp => app.scroll_log(LOG_PAGE),
+
+                KeyCode::PageDown => app.scroll_log(-LOG_PAGE),
+
+                KeyCode::Up => app.move_selection(-1),
+
+                KeyCode::Down => app.move_selection(1),
+
+                KeyCode::Tab => {
+                    app.input_focus = (app.input_focus + 1) % ui::INPUT_FIELDS;
+                }
+
+                KeyCode::Char(c) => {
+                    app.handle_char_input(c);
+                }
+
+                KeyCode::Backspace => {
+                    app.handle_backspace();
+                }
+
+                _ => {}
+            }
+        }
+
+        if let Some(rx) = trace_rx.as_mut() {
+            while let Ok(line) = rx.try_recv() {
+                app.push_log(line);
+            }
+        }
+
+        app.sample_rate();
+        let mut applied = 0;
+        while app.result_batch.is_none_or(|max| applied < max) && let Ok(event) = app.rx.try_recv() {
+            applied += 1;
+            if matches!(event, ScanEvent::Finished { .. }) {
+                app.live_writer = None;
+            }
+            match event {
+                ScanEvent::Result(result) => app.apply_result(result),
+                ScanEvent::Progress { done, .. } => app.apply_progress(done),
+                ScanEvent::Finished { timed_out: true, elapsed, .. } => {
+                    // Like a cancel: rows the scan never reached stay, greyed.
+                    app.is_scanning = false;
+                    app.finish_job("deadline");
+                    app.last_summary = Some(app.scan_summary());
+                    app.scan_finished_wall = Some(SystemTime::now());
+                    app.push_log(format!(
+                        "Scan hit deadline after {}s: {} of {} probes done",
+                        elapsed.as_secs(),
+                        app.total_scanned,
+                        app.total_ports
+                    ));
+                    scan_task.take();
+                }
+                ScanEvent::Finished { cancelled: true, .. } => {
+                    // Cached rows the scan never reached stay visible (greyed) rather than dropped.
+                    app.is_scanning = false;
+                    app.finish_job("cancelled");
+                    let elapsed = app.scan_elapsed().unwrap_or_default();
+                    app.push_log(format!(
+                        "Scan cancelled after {:.2}s: {} of {} probes done",
+                        elapsed.as_secs_f64(),
+                        app.total_scanned,
+                        app.total_ports
+                    ));
+                    scan_task.take();
+                }
+                ScanEvent::Finished { cancelled: false, .. } => {
+                    if !app.complete_scan() {
+                        // Failed: the status shows the error; no summary or hints.
+                        scan_task.take();
+                        continue;
+                    }
+                    if let Some(n) = scan_config.stop_after_open && app.open_ports().len() >= n {
+                        app.push_log(format!("Stopped after {} open ports", n));
+                    }
+                    scan_task.take();
+                    let latency: LatencySamples = app.results.iter().collect();
+                    for line in latency.summary(app.latency_closed) {
+                        app.push_log(line);
+                    }
+
+                    let timeouts = app.results.iter().filter(|r| r.status == "filtered").count();
+                    let ratio = timeouts as f64 / app.results.len().max(1) as f64;
+                    if ratio >= RETRY_TIMEOUT_RATIO && let Some(last) = &last_scan {
+                        let longer = last.timeout * 2;
+                        retry_timeout = Some(longer);
+                        app.push_log(format!(
+                            "{:.0}% of ports timed out; press F12 to rescan with a {}ms timeout",
+                            ratio * 100.0,
+                            longer.as_millis()
+                        ));
+                    }
+                }
+                ScanEvent::Error(error) => {
+                    // The target never resolved; the Finished that follows ends the scan.
+                    app.fail_scan(error);
+                }
+            }
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            last_tick = Instant::now();
+        }
+    }
+
+    Ok(())
+}
@@ -1,92 +1,1279 @@
-/// UI module for the Night Tool application.
-/// It defines the application state and rendering logic using the Ratatui library.
-/// # Imports
-/// - `crate::scanner::ScanResult` - Struct representing the result of a port scan.
-/// - `ratatui` - Library for building terminal user interfaces.
-/// - `tokio::sync::mpsc` - Tokio's multi-producer, single-consumer channel for asynchronous communication.
-/// - `std::time::Instant` - Standard library time utility for measuring elapsed time.
-/// # Structs
-/// - `App` - Struct representing the application state, including user inputs, scan results, log events, and scanning status.
-/// # Functions
-/// - `draw(f: &mut Frame, app: &App)` - Renders the entire UI based on the current application state.
-/// - `draw_top_bar(f: &mut Frame, area: Rect, app: &App)` - Renders the top bar of the UI displaying target info and status.
-/// - `draw_main(f: &mut Frame, area: Rect, app: &App)` - Renders the main area of the UI showing scan results and details.
-/// - `draw_bottom_bar(f: &mut Frame, area: Rect, app: &App)` - Renders the bottom bar of the UI with control instructions.
-/// # Examples
-/// ```no_run
-/// use ratatui::Terminal;
-/// use crate::ui::{App, draw};
-/// let mut app = App::new(rx);
-/// terminal.draw(|f| draw(f, &app))?;
-/// ```
-
-use crate::scanner::ScanResult;
+//! UI module for the Night Tool application.
+//! It defines the application state and rendering logic using the Ratatui library.
+//! # Imports
+//! - `crate::scanner::{ScanEvent, ScanResult}` - Messages of a running scan, and the result of a port scan.
+//! - `ratatui` - Library for building terminal user interfaces.
+//! - `tokio::sync::mpsc` - Tokio's multi-producer, single-consumer channel for asynchronous communication.
+//! - `std::time::Instant` - Standard library time utility for measuring elapsed time.
+//! # Structs
+//! - `Theme` - Colors used for result states, shared by the results table and the legend.
+//! - `ScanJob` - Metadata of one launched scan, kept for the audit log.
+//! - `ScanSummary` - End-of-scan report shown in a popup when a scan completes.
+//! - `App` - Struct representing the application state, including user inputs, scan results, log events, and scanning status.
+//! # Functions
+//! - `draw(f: &mut Frame, app: &App)` - Renders the entire UI based on the current application state.
+//! - `draw_top_bar(f: &mut Frame, area: Rect, app: &App)` - Renders the top bar of the UI displaying target info and status.
+//! - `draw_main(f: &mut Frame, area: Rect, app: &App)` - Renders the main area of the UI showing scan results and details.
+//! - `draw_bottom_bar(f: &mut Frame, area: Rect, app: &App)` - Renders the bottom bar of the UI with the color legend and control instructions.
+//! # Examples
+//! ```ignore
+//! use ratatui::Terminal;
+//! use crate::ui::{App, draw};
+//! let mut app = App::new(rx);
+//! terminal.draw(|f| draw(f, &app))?;
+//! ```
+
+use crate::config::{Profile, SavedNote, UiPrefs, DEFAULT_PROFILES_PATH};
+use crate::export::LiveJsonl;
+use crate::ports::parse_port_spec;
+use crate::scanner::{self, Protocol, ScanEvent, ScanResult};
+use crate::stats;
+use crate::throttle::ThrottleDetector;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Paragraph, Row, Table},
+    text::{Line, Span},
+    widgets::{BarChart, Block, Borders, Clear, Gauge, Paragraph, Row, Table, TableState, Wrap},
     Frame,
 };
 use tokio::sync::mpsc;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Colors used to render result states. The results table and the legend both read from it.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub open: Color,
+    pub closed: Color,
+    pub filtered: Color,
+    pub unreachable: Color,
+    pub other: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            open: Color::Green,
+            closed: Color::Gray,
+            filtered: Color::Yellow,
+            unreachable: Color::LightRed,
+            other: Color::White,
+        }
+    }
+}
+
+impl Theme {
+    pub fn status_color(&self, status: &str) -> Color {
+        match status {
+            "open" => self.open,
+            "closed" | "reset" => self.closed,
+            "filtered" => self.filtered,
+            "unreachable" => self.unreachable,
+            _ => self.other,
+        }
+    }
+
+    /// Status labels in legend order, paired with their color.
+    pub fn legend(&self) -> Vec<(&'static str, Color)> {
+        ["open", "closed", "filtered", "unreachable"]
+            .into_iter()
+            .map(|s| (s, self.status_color(s)))
+            .collect()
+    }
+}
+
+/// Column the Results table is ordered by. `results` itself always stays in arrival order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Arrival,
+    Port,
+    Response,
+    Service,
+}
+
+impl SortBy {
+    /// The column after this one, wrapping back to arrival order.
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Arrival => SortBy::Port,
+            SortBy::Port => SortBy::Response,
+            SortBy::Response => SortBy::Service,
+            SortBy::Service => SortBy::Arrival,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Arrival => "arrival",
+            SortBy::Port => "port",
+            SortBy::Response => "response",
+            SortBy::Service => "service",
+        }
+    }
+}
+
+/// One scan launched in this session, recorded for the audit log.
+#[derive(Clone, Debug)]
+pub struct ScanJob {
+    pub host: String,
+    /// What was probed, e.g. "ports 1-1000" or "top ports".
+    pub scope: String,
+    pub started: SystemTime,
+    pub finished: Option<SystemTime>,
+    /// "completed", "failed", "cancelled", "deadline" or "aborted"; `None` while running.
+    pub outcome: Option<String>,
+    pub scanned: usize,
+    pub open: usize,
+}
+
+/// End-of-scan report, shown as a popup when a scan completes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScanSummary {
+    pub host: String,
+    /// What was probed, as in `ScanJob::scope`.
+    pub scope: String,
+    pub scanned: usize,
+    pub open: usize,
+    pub closed: usize,
+    /// Silent ports, UDP `open|filtered` included.
+    pub filtered: usize,
+    /// Resets, unreachable ports and local errors.
+    pub other: usize,
+    /// Time spent scanning, pauses excluded.
+    pub duration: Duration,
+    /// Mean response time of open and closed ports; `None` when none answered.
+    pub avg_response_ms: Option<u128>,
+    /// `port/protocol service` of every open port, prefixed with the address when
+    /// results come from more than one machine.
+    pub open_ports: Vec<String>,
+}
+
+/// Number of Tab-focusable inputs in the top bar.
+pub const INPUT_FIELDS: usize = 8;
+
+/// Top-ports scan sizes Ctrl+T cycles through.
+pub const TOP_COUNT_CHOICES: &[usize] = &[10, 100, 1000];
+
+/// Shortest connect timeout accepted from the Timeout field.
+pub const MIN_TIMEOUT_MS: u64 = 100;
+
+/// Key bindings listed by the F1 help popup.
+pub const KEY_HELP: &[(&str, &str)] = &[
+    ("S / Enter", "Start scan (End may hold a port list, e.g. 22,80,8000-8100)"),
+    ("T", "Quick scan of popular TCP ports"),
+    ("Ctrl+U", "Quick scan of common UDP services"),
+    ("C", "Cancel scan; press again to abort at once"),
+    ("Ctrl+P", "Pause or resume the running scan"),
+    ("Ctrl+R", "Toggle random port order for the next scan"),
+    ("Ctrl+O", "Toggle showing only open ports"),
+    ("Ctrl+L", "Clear the log"),
+    ("Ctrl+K / Ctrl+D", "Sort results by the next column / reverse the order"),
+    ("Tab", "Switch input field"),
+    ("Up / Down", "Move the selection in the Results table"),
+    ("PgUp / PgDn", "Scroll the Log back / forward (Shift+Up / Shift+Down: one line)"),
+    ("Ctrl+W / Ctrl+E", "Export results as JSON / CSV"),
+    ("Ctrl+G", "Go to port"),
+    ("Ctrl+F", "Search results by service or banner (Up recalls the last search)"),
+    ("F11 / Shift+F11", "Jump to the next / previous match of the last search"),
+    ("Ctrl+S", "Save the inputs as a profile"),
+    ("Ctrl+A", "Write the audit log"),
+    ("Ctrl+B", "Toggle the response-time histogram"),
+    ("Ctrl+N", "Toggle banner grabbing for the next scan"),
+    ("Ctrl+T", "Cycle the top-ports scan size (top 10 / 100 / 1000)"),
+    ("F1", "Show this help"),
+    ("F2", "Toggle merging of repeated log lines"),
+    ("F3", "Add or edit a note on the selected result"),
+    ("F4", "Toggle compact Results table"),
+    ("F5", "Export results as nmap XML"),
+    ("F6", "Clear the result cache"),
+    ("F7", "Copy results to the clipboard"),
+    ("F8", "Write a text report"),
+    ("F9", "Cycle through profiles"),
+    ("F10", "Toggle the by-service view"),
+    ("F12", "Rescan with twice the timeout"),
+    ("Q / Esc / Ctrl+C", "Quit"),
+];
+
+/// Most log events kept; older ones are dropped as new ones arrive.
+pub const MAX_LOG_EVENTS: usize = 1000;
+
+/// Span of recent progress the ports/sec counter is computed over.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
 
 pub struct App {
     pub host_input: String,
     pub start_port_input: String,
     pub end_port_input: String,
+    /// Max simultaneous probes. Empty uses the flag, profile or scan mode default.
+    pub concurrency_input: String,
+    /// Connect timeout in milliseconds. Empty uses the scan mode default.
+    pub timeout_ms_input: String,
+    /// Most connection attempts started per second. Empty leaves the rate uncapped.
+    pub max_pps_input: String,
+    /// SOCKS5 proxy address (`host:port` as an IP). Empty connects directly.
+    pub proxy_input: String,
+    /// Extra attempts for silent ports. Empty uses the flag or scan mode default.
+    pub retries_input: String,
     pub results: Vec<ScanResult>,
     pub log_events: Vec<String>,
+    /// When each entry of `log_events` was first logged.
+    log_times: Vec<SystemTime>,
+    /// Events hidden below the Log pane while reading older ones; 0 follows new events.
+    pub log_scroll: usize,
     pub is_scanning: bool,
     pub input_focus: usize,
-    pub rx: mpsc::Receiver<ScanResult>,
+    pub rx: mpsc::Receiver<ScanEvent>,
+    /// Probes the current scan has finished: the larger of the results received and
+    /// the last `ScanEvent::Progress`, which also counts results that were not sent.
     pub total_scanned: usize,
+    results_received: usize,
+    /// Probes the current scan makes in all (ports × addresses × protocols), set at launch.
+    pub total_ports: usize,
     pub started_at: Option<Instant>,
+    /// (time, `total_scanned`) samples covering the last `RATE_WINDOW` of the running scan.
+    rate_samples: VecDeque<(Instant, usize)>,
+    /// Average rate of the last scan, shown once it has ended.
+    final_rate: Option<f64>,
+    pub coalesce_log: bool,
+    log_repeat: usize,
+    pub scan_host: String,
+    /// Free-text notes per (result host, port, protocol), saved in the state file.
+    pub notes: HashMap<(String, u16, Protocol), String>,
+    /// The result whose note is being typed, and the text so far.
+    pub note_input: Option<((String, u16, Protocol), String)>,
+    pub theme: Theme,
+    pub scan_started_wall: Option<SystemTime>,
+    pub scan_finished_wall: Option<SystemTime>,
+    /// Most recent result per (result host, port, protocol), kept across scans in this session.
+    pub result_cache: HashMap<(String, u16, Protocol), ScanResult>,
+    /// Rows pre-filled from the cache that the running scan has not refreshed yet.
+    stale_rows: HashMap<(String, u16, Protocol), usize>,
+    throttle: ThrottleDetector,
+    /// Services seen among open results of the current scan.
+    services_seen: HashSet<String>,
+    pub profiles: Vec<Profile>,
+    /// Index into `profiles` of the profile that filled the inputs; cleared on manual edits.
+    pub active_profile: Option<usize>,
+    /// File Ctrl+S saves the current inputs to as a profile (`--profiles`).
+    pub profiles_path: PathBuf,
+    /// Results table shows only Port and State, fitting more rows on screen.
+    pub compact: bool,
+    /// Show socket error strings in the Detail pane (`--verbose`).
+    pub verbose: bool,
+    /// Replace the Results table with open results grouped by service.
+    pub group_by_service: bool,
+    /// Probe ports of the next scan in random order (`--shuffle`).
+    pub shuffle: bool,
+    /// How many of the ranked top ports T scans; Ctrl+T cycles through `TOP_COUNT_CHOICES`.
+    pub top_count: usize,
+    /// Read banners from open TCP ports of the next scan. Off, only connectivity is checked.
+    pub grab_banners: bool,
+    /// JSON Lines file each scan appends its results to as they arrive (`--live-jsonl`).
+    pub live_path: Option<PathBuf>,
+    /// Open while a scan streams to `live_path`.
+    pub live_writer: Option<LiveJsonl>,
+    /// Hide every row of the Results table that is not open. Counters are unaffected.
+    pub show_only_open: bool,
+    pub sort_by: SortBy,
+    /// Reverse the Results order (e.g. slowest or newest first).
+    pub sort_desc: bool,
+    /// The F1 help popup is open; the next key only closes it.
+    pub show_help: bool,
+    /// Report of the scan that just completed, shown as a popup until the next key.
+    pub last_summary: Option<ScanSummary>,
+    /// Show the response-time histogram below the Results table.
+    pub show_histogram: bool,
+    /// Scan every address the target resolves to (`--all-addresses`).
+    pub all_addresses: bool,
+    pub scan_jobs: Vec<ScanJob>,
+    /// Closed, filtered and other results of the running scan that were counted instead
+    /// of sent (`--open-only`); added to the scan summary.
+    pub unsent: Option<Arc<scanner::UnsentCounts>>,
+    /// Cancellation flag shared with the running scan.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Pause flag shared with the running scan.
+    pub pause: Option<Arc<AtomicBool>>,
+    pub is_paused: bool,
+    /// Why the last scan failed before probing anything (e.g. the host didn't resolve).
+    /// Shown in the Status box until the next scan starts.
+    pub scan_error: Option<String>,
+    /// When the current pause began.
+    paused_since: Option<Instant>,
+    /// Time the current scan spent paused, not counting a pause still in progress.
+    paused_total: Duration,
+    /// Who runs the scans, for the audit log (`operator` in the config file).
+    pub operator: Option<String>,
+    /// Row of `results` selected in the Results table; `None` follows the newest result.
+    pub selected: Option<usize>,
+    /// Port number being typed into the go-to-port prompt.
+    pub goto_input: Option<String>,
+    /// Only results whose service or banner contain this (ignoring case) are shown.
+    /// Empty shows everything.
+    pub filter_input: String,
+    /// Keystrokes go to the search prompt (`filter_input`).
+    pub filter_editing: bool,
+    /// The last search confirmed with Enter, for recalling it and jumping between its
+    /// matches. Cleared when the search is reset with Esc.
+    pub last_query: String,
+    /// Most results applied per loop iteration; `None` drains everything available.
+    pub result_batch: Option<usize>,
+    /// Count closed ports in the Counters latency and report latency over them when a
+    /// scan finishes (`--latency-closed`).
+    pub latency_closed: bool,
+    /// Response times of the current scan's open (and, with `latency_closed`, closed)
+    /// results. Silent ports are left out: their time is just the timeout.
+    pub latency: stats::RunningLatency,
+}
+
+/// Whether `r`'s service or banner contains `query`, ignoring case. An empty query matches.
+fn matches_query(r: &ScanResult, query: &str) -> bool {
+    let needle = query.trim().to_lowercase();
+    needle.is_empty()
+        || r.service.to_lowercase().contains(&needle)
+        || r.banner.as_ref().is_some_and(|b| b.to_lowercase().contains(&needle))
 }
 
 impl App {
-    pub fn new(rx: mpsc::Receiver<ScanResult>) -> Self {
+    pub fn new(rx: mpsc::Receiver<ScanEvent>) -> Self {
         Self {
             host_input: "".to_string(),
             start_port_input: "1".to_string(),
             end_port_input: "1000".to_string(),
+            concurrency_input: String::new(),
+            timeout_ms_input: String::new(),
+            max_pps_input: String::new(),
+            proxy_input: String::new(),
+            retries_input: String::new(),
             results: Vec::new(),
             log_events: Vec::new(),
+            log_times: Vec::new(),
+            log_scroll: 0,
             is_scanning: false,
             input_focus: 0,
             rx,
             total_scanned: 0,
+            results_received: 0,
+            total_ports: 0,
             started_at: None,
+            rate_samples: VecDeque::new(),
+            final_rate: None,
+            coalesce_log: true,
+            log_repeat: 1,
+            scan_host: "".to_string(),
+            notes: HashMap::new(),
+            note_input: None,
+            theme: Theme::default(),
+            scan_started_wall: None,
+            scan_finished_wall: None,
+            result_cache: HashMap::new(),
+            stale_rows: HashMap::new(),
+            throttle: ThrottleDetector::default(),
+            services_seen: HashSet::new(),
+            profiles: Vec::new(),
+            active_profile: None,
+            profiles_path: PathBuf::from(DEFAULT_PROFILES_PATH),
+            compact: false,
+            verbose: false,
+            group_by_service: false,
+            show_only_open: false,
+            sort_by: SortBy::default(),
+            sort_desc: false,
+            show_help: false,
+            last_summary: None,
+            show_histogram: false,
+            shuffle: false,
+            top_count: 100,
+            grab_banners: true,
+            live_path: None,
+            live_writer: None,
+            all_addresses: false,
+            scan_jobs: Vec::new(),
+            unsent: None,
+            cancel: None,
+            pause: None,
+            is_paused: false,
+            scan_error: None,
+            paused_since: None,
+            paused_total: Duration::ZERO,
+            operator: None,
+            selected: None,
+            goto_input: None,
+            filter_input: String::new(),
+            filter_editing: false,
+            last_query: String::new(),
+            result_batch: None,
+            latency_closed: false,
+            latency: stats::RunningLatency::default(),
+        }
+    }
+
+    /// Appends a log event, dropping the oldest beyond `MAX_LOG_EVENTS`. With
+    /// `coalesce_log` enabled, a message identical to the previous one replaces it
+    /// with a "(xN)" repeat count instead of adding a new line.
+    pub fn push_log(&mut self, msg: impl Into<String>) {
+        let msg = msg.into();
+        if self.coalesce_log && let Some(last) = self.log_events.last_mut() {
+            let same = if self.log_repeat > 1 {
+                *last == format!("{} (x{})", msg, self.log_repeat)
+            } else {
+                *last == msg
+            };
+            if same {
+                self.log_repeat += 1;
+                *last = format!("{} (x{})", msg, self.log_repeat);
+                return;
+            }
+        }
+        self.log_repeat = 1;
+        self.log_events.push(msg);
+        self.log_times.push(SystemTime::now());
+        // A reader scrolled back keeps looking at the same events.
+        if self.log_scroll > 0 {
+            self.log_scroll += 1;
+        }
+        if self.log_events.len() > MAX_LOG_EVENTS {
+            let excess = self.log_events.len() - MAX_LOG_EVENTS;
+            self.log_events.drain(..excess);
+            self.log_times.drain(..excess);
+            self.log_scroll = self.log_scroll.min(self.log_events.len() - 1);
+        }
+    }
+
+    /// Flips merging of repeated log lines (F2) and logs the new setting.
+    pub fn toggle_coalesce_log(&mut self) {
+        self.coalesce_log = !self.coalesce_log;
+        let state = if self.coalesce_log { "on" } else { "off" };
+        self.push_log(format!("Log coalescing {}", state));
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log_events.clear();
+        self.log_times.clear();
+        self.log_repeat = 1;
+        self.log_scroll = 0;
+    }
+
+    /// Scrolls the Log pane back (`delta` > 0) or forward through the history, keeping
+    /// at least the oldest event in view. Scrolling forward to the end follows new events again.
+    pub fn scroll_log(&mut self, delta: isize) {
+        let max = self.log_events.len().saturating_sub(1);
+        self.log_scroll = self.log_scroll.saturating_add_signed(delta).min(max);
+    }
+
+    /// Catches `total_scanned` up with a `ScanEvent::Progress` count.
+    pub fn apply_progress(&mut self, done: usize) {
+        self.total_scanned = self.total_scanned.max(done);
+    }
+
+    /// Completed share of the current scan, from 0.0 to 1.0. Full once the scan has finished.
+    pub fn progress(&self) -> f64 {
+        if self.scan_finished_wall.is_some() && !self.is_scanning {
+            return 1.0;
+        }
+        if self.total_ports == 0 {
+            return 0.0;
+        }
+        (self.total_scanned as f64 / self.total_ports as f64).min(1.0)
+    }
+
+    /// Records the current progress for the ports/sec counter. Called once per UI tick.
+    pub fn sample_rate(&mut self) {
+        if !self.is_scanning {
+            return;
+        }
+        let now = Instant::now();
+        self.rate_samples.push_back((now, self.total_scanned));
+        // Keep one sample at or beyond the window edge so the span covers the full window.
+        while self.rate_samples.len() > 2 && now.duration_since(self.rate_samples[1].0) >= RATE_WINDOW {
+            self.rate_samples.pop_front();
+        }
+    }
+
+    /// Probes per second: over the last few seconds while scanning, and the whole-scan
+    /// average once the scan has ended. `None` before the first scan.
+    pub fn scan_rate(&self) -> Option<f64> {
+        if !self.is_scanning {
+            return self.final_rate;
+        }
+        if let (Some(&(t0, n0)), Some(&(t1, n1))) = (self.rate_samples.front(), self.rate_samples.back()) {
+            let span = t1.duration_since(t0).as_secs_f64();
+            if span >= 1.0 {
+                return Some((n1 - n0) as f64 / span);
+            }
+        }
+        let elapsed = self.scan_elapsed()?.as_secs_f64();
+        (elapsed > 0.0).then(|| self.total_scanned as f64 / elapsed)
+    }
+
+    /// Pauses or resumes the running scan. Returns whether it is now paused, or
+    /// `None` when no scan is running.
+    pub fn toggle_pause(&mut self) -> Option<bool> {
+        let pause = self.pause.as_ref().filter(|_| self.is_scanning)?;
+        self.is_paused = !self.is_paused;
+        pause.store(self.is_paused, Ordering::Relaxed);
+        if self.is_paused {
+            self.paused_since = Some(Instant::now());
+        } else if let Some(t0) = self.paused_since.take() {
+            self.paused_total += t0.elapsed();
+        }
+        Some(self.is_paused)
+    }
+
+    /// Time the current or last scan has spent running, pauses excluded.
+    pub fn scan_elapsed(&self) -> Option<Duration> {
+        let paused = self.paused_total + self.paused_since.map_or(Duration::ZERO, |t0| t0.elapsed());
+        Some(self.started_at?.elapsed().saturating_sub(paused))
+    }
+
+    /// Time left in the running scan, extrapolated from progress so far. `None` when
+    /// no scan is running or nothing has completed yet.
+    pub fn eta(&self) -> Option<Duration> {
+        if !self.is_scanning || self.total_scanned == 0 || self.total_ports == 0 {
+            return None;
+        }
+        let elapsed = self.scan_elapsed()?;
+        let remaining = self.total_ports.saturating_sub(self.total_scanned);
+        Some(elapsed.mul_f64(remaining as f64 / self.total_scanned as f64))
+    }
+
+    /// Log events paired with the time they were first logged.
+    pub fn log_entries(&self) -> impl Iterator<Item = (SystemTime, &str)> {
+        self.log_times.iter().copied().zip(self.log_events.iter().map(String::as_str))
+    }
+
+    /// Records the start of a scan job for the audit log.
+    pub fn start_job(&mut self, host: &str, scope: impl Into<String>) {
+        self.scan_jobs.push(ScanJob {
+            host: host.to_string(),
+            scope: scope.into(),
+            started: SystemTime::now(),
+            finished: None,
+            outcome: None,
+            scanned: 0,
+            open: 0,
+        });
+    }
+
+    /// Summarizes the results of the current scan. Stale cached rows are left out.
+    pub fn scan_summary(&self) -> ScanSummary {
+        let fresh: Vec<&ScanResult> = self.results.iter().filter(|r| !self.is_stale(r)).collect();
+        let count = |statuses: &[&str]| fresh.iter().filter(|r| statuses.contains(&r.status.as_str())).count();
+        let answered: Vec<u128> = fresh.iter().filter(|r| matches!(r.status.as_str(), "open" | "closed")).map(|r| r.response_ms).collect();
+        let open_ports: Vec<String> = self.open_ports().iter().map(|r| match self.multi_target() {
+            true => format!("{} {}/{} {}", r.host, r.port, r.protocol, r.service),
+            false => format!("{}/{} {}", r.port, r.protocol, r.service),
+        }).collect();
+        let (open, closed, filtered) = (count(&["open"]), count(&["closed"]), count(&["filtered", "open|filtered"]));
+        let (unsent_closed, unsent_filtered, unsent_other) = self.unsent.as_deref().map_or((0, 0, 0), scanner::UnsentCounts::snapshot);
+        let job = self.scan_jobs.last();
+        ScanSummary {
+            host: job.map_or_else(|| self.scan_host.clone(), |j| j.host.clone()),
+            scope: job.map(|j| j.scope.clone()).unwrap_or_default(),
+            scanned: self.total_scanned,
+            open,
+            closed: closed + unsent_closed,
+            filtered: filtered + unsent_filtered,
+            other: fresh.len() - open - closed - filtered + unsent_other,
+            duration: self.scan_elapsed().unwrap_or_default(),
+            avg_response_ms: (!answered.is_empty()).then(|| answered.iter().sum::<u128>() / answered.len() as u128),
+            open_ports,
+        }
+    }
+
+    /// Closes the running scan job, if any, with its final counts, and freezes the
+    /// ports/sec counter at the scan's average rate.
+    pub fn finish_job(&mut self, outcome: &str) {
+        let open = self.open_ports().len();
+        let scanned = self.total_scanned;
+        if let Some(elapsed) = self.scan_elapsed() {
+            let elapsed = elapsed.as_secs_f64();
+            self.final_rate = (elapsed > 0.0).then(|| scanned as f64 / elapsed);
+        }
+        self.rate_samples.clear();
+        if let Some(t0) = self.paused_since.take() {
+            self.paused_total += t0.elapsed();
+        }
+        self.is_paused = false;
+        if let Some(job) = self.scan_jobs.last_mut().filter(|j| j.outcome.is_none()) {
+            job.finished = Some(SystemTime::now());
+            job.outcome = Some(outcome.to_string());
+            job.scanned = scanned;
+            job.open = open;
+        }
+    }
+
+    /// Resets the results for a new scan of `host`. Cached results for that host are
+    /// shown immediately as stale rows and replaced as fresh results arrive.
+    pub fn begin_scan(&mut self, host: &str) {
+        self.results.clear();
+        self.selected = None;
+        self.stale_rows.clear();
+        self.total_scanned = 0;
+        self.results_received = 0;
+        self.started_at = Some(Instant::now());
+        self.paused_since = None;
+        self.paused_total = Duration::ZERO;
+        self.is_paused = false;
+        self.scan_error = None;
+        self.last_summary = None;
+        self.latency = stats::RunningLatency::default();
+        self.rate_samples.clear();
+        self.final_rate = None;
+        self.scan_host = host.to_string();
+        self.throttle = ThrottleDetector::default();
+        self.services_seen.clear();
+
+        // Results name IP targets canonically (`[::1]` as `::1`), and a CIDR scan
+        // reports each address as its own host.
+        let targets: Vec<String> = match scanner::parse_targets(host) {
+            ips if ips.is_empty() => vec![host.to_string()],
+            ips => ips.iter().map(|ip| ip.to_string()).collect(),
+        };
+        let mut cached: Vec<&ScanResult> = self.result_cache.iter()
+            .filter(|((h, _, _), _)| targets.contains(h))
+            .map(|(_, r)| r)
+            .collect();
+        cached.sort_by_key(|r| (r.port, r.protocol == Protocol::Udp, r.host.clone()));
+        for r in cached {
+            self.stale_rows.insert((r.host.clone(), r.port, r.protocol), self.results.len());
+            self.results.push(r.clone());
+        }
+    }
+
+    /// Records a fresh result, replacing its stale cached row if there is one.
+    pub fn apply_result(&mut self, result: ScanResult) {
+        if let Some(w) = &mut self.live_writer && let Err(e) = w.write(&result) {
+            self.live_writer = None;
+            self.push_log(format!("Stopped streaming results: {}", e));
+        }
+        self.results_received += 1;
+        self.total_scanned = self.total_scanned.max(self.results_received);
+        if let Some(warning) = self.throttle.observe(&result) {
+            self.push_log(warning);
+        }
+        if result.status == "open" || (self.latency_closed && result.status == "closed") {
+            self.latency.observe(result.response_ms);
+        }
+        if result.status == "open" && !self.services_seen.contains(&result.service) {
+            self.services_seen.insert(result.service.clone());
+        }
+        let key = (result.host.clone(), result.port, result.protocol);
+        self.result_cache.insert(key.clone(), result.clone());
+        match self.stale_rows.remove(&key) {
+            Some(idx) => self.results[idx] = result,
+            None => self.results.push(result),
+        }
+    }
+
+    /// Drops cached rows the finished scan did not cover.
+    pub fn finish_scan(&mut self) {
+        let leftover: HashSet<usize> = self.stale_rows.drain().map(|(_, idx)| idx).collect();
+        if !leftover.is_empty() {
+            self.selected = None;
+            let mut idx = 0;
+            self.results.retain(|_| {
+                idx += 1;
+                !leftover.contains(&(idx - 1))
+            });
+        }
+    }
+
+    /// Records why the running scan failed (e.g. the target never resolved) and logs it.
+    pub fn fail_scan(&mut self, error: String) {
+        self.push_log(format!("Scan failed: {}", error));
+        self.scan_error = Some(error);
+    }
+
+    /// Ends a scan that was neither cancelled nor cut off by the deadline. A scan that
+    /// failed is closed as "failed" with no summary and returns false; otherwise the
+    /// job is closed as "completed", the summary popup is set and the duration logged.
+    pub fn complete_scan(&mut self) -> bool {
+        self.is_scanning = false;
+        self.finish_scan();
+        if self.scan_error.is_some() {
+            self.finish_job("failed");
+            return false;
+        }
+        self.finish_job("completed");
+        self.last_summary = Some(self.scan_summary());
+        self.scan_finished_wall = Some(SystemTime::now());
+        match self.scan_elapsed() {
+            Some(elapsed) => self.push_log(format!("Scan finished in {:.2}s", elapsed.as_secs_f64())),
+            None => self.push_log("Scan finished".to_string()),
+        }
+        true
+    }
+
+    pub fn is_stale(&self, r: &ScanResult) -> bool {
+        self.stale_rows.contains_key(&(r.host.clone(), r.port, r.protocol))
+    }
+
+    /// Whether results come from more than one machine (CIDR target or `--all-addresses`),
+    /// so rows need their address shown.
+    pub fn multi_target(&self) -> bool {
+        self.all_addresses || self.scan_host.contains('/')
+    }
+
+    /// Number of distinct services among the open ports found by the current scan.
+    pub fn distinct_services(&self) -> usize {
+        self.services_seen.len()
+    }
+
+    /// Open ports confirmed by the current scan, by port and protocol. Stale rows
+    /// carried over from the cache are left out until they are re-probed.
+    pub fn open_ports(&self) -> Vec<&ScanResult> {
+        let mut open: Vec<&ScanResult> = self.results.iter()
+            .filter(|r| r.status == "open" && !self.is_stale(r))
+            .collect();
+        open.sort_by_key(|r| (r.port, r.protocol.to_string(), r.host.clone()));
+        open
+    }
+
+    /// Open results grouped by service name, most common service first (ties by name).
+    pub fn service_groups(&self) -> Vec<(&str, Vec<&ScanResult>)> {
+        let mut groups: HashMap<&str, Vec<&ScanResult>> = HashMap::new();
+        for r in self.results.iter().filter(|r| r.status == "open") {
+            groups.entry(r.service.as_str()).or_default().push(r);
+        }
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        for (_, rs) in &mut groups {
+            rs.sort_by_key(|r| (r.port, r.protocol.to_string()));
+        }
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+        groups
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.result_cache.clear();
+    }
+
+    /// Indices into `results` of the rows the Results table shows, in display order.
+    /// Rows that tie on the sort column keep their arrival order.
+    pub fn visible_rows(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = (0..self.results.len())
+            .filter(|&i| !self.show_only_open || self.results[i].status == "open")
+            .filter(|&i| matches_query(&self.results[i], &self.filter_input))
+            .collect();
+        let r = &self.results;
+        rows.sort_by(|&a, &b| {
+            let order = match self.sort_by {
+                SortBy::Arrival => a.cmp(&b),
+                SortBy::Port => r[a].port.cmp(&r[b].port),
+                SortBy::Response => r[a].response_ms.cmp(&r[b].response_ms),
+                SortBy::Service => r[a].service.cmp(&r[b].service),
+            };
+            if self.sort_desc { order.reverse() } else { order }
+        });
+        rows
+    }
+
+    /// Orders the Results table by the next column.
+    pub fn cycle_sort(&mut self) {
+        self.sort_by = self.sort_by.next();
+    }
+
+    /// Flips the open-only filter, dropping a selection the filter hides.
+    pub fn toggle_show_only_open(&mut self) {
+        self.show_only_open = !self.show_only_open;
+        self.drop_hidden_selection();
+    }
+
+    /// Replaces the search text, dropping a selection it hides.
+    pub fn set_filter(&mut self, text: String) {
+        self.filter_input = text;
+        self.drop_hidden_selection();
+    }
+
+    /// Closes the search prompt, keeping its text as `last_query` unless it is empty.
+    pub fn confirm_filter(&mut self) {
+        self.filter_editing = false;
+        if !self.filter_input.trim().is_empty() {
+            self.last_query = self.filter_input.clone();
+        }
+    }
+
+    /// Closes the search prompt and forgets both the search and `last_query`.
+    pub fn reset_filter(&mut self) {
+        self.filter_editing = false;
+        self.last_query.clear();
+        self.set_filter(String::new());
+    }
+
+    /// Selects the next (or, with `forward` false, previous) shown row matching
+    /// `last_query`, wrapping at the ends. Returns false if no shown row matches.
+    pub fn jump_to_match(&mut self, forward: bool) -> bool {
+        if self.last_query.trim().is_empty() {
+            return false;
+        }
+        let visible = self.visible_rows();
+        let hits: Vec<usize> = (0..visible.len())
+            .filter(|&pos| matches_query(&self.results[visible[pos]], &self.last_query))
+            .collect();
+        let (Some(&first), Some(&last)) = (hits.first(), hits.last()) else { return false };
+        let current = self.selected.and_then(|i| visible.iter().position(|&v| v == i));
+        let pos = match (current, forward) {
+            (Some(cur), true) => hits.iter().copied().find(|&p| p > cur).unwrap_or(first),
+            (Some(cur), false) => hits.iter().copied().rev().find(|&p| p < cur).unwrap_or(last),
+            (None, true) => first,
+            (None, false) => last,
+        };
+        self.selected = Some(visible[pos]);
+        true
+    }
+
+    fn drop_hidden_selection(&mut self) {
+        if self.selected.is_some_and(|i| !self.visible_rows().contains(&i)) {
+            self.selected = None;
+        }
+    }
+
+    /// The result shown in the Detail pane: the selected row, else the newest visible result.
+    pub fn detail_result(&self) -> Option<&ScanResult> {
+        self.selected
+            .or_else(|| self.visible_rows().into_iter().max())
+            .and_then(|i| self.results.get(i))
+    }
+
+    /// Moves the Results selection by `delta` shown rows, clamped to the table. With no
+    /// selection it starts from the newest row. The selection follows its result, not
+    /// its position, so the cursor stays put as results arrive during a live scan.
+    pub fn move_selection(&mut self, delta: isize) {
+        let visible = self.visible_rows();
+        let Some(last) = visible.len().checked_sub(1) else { return };
+        let newest = visible.iter().copied().max().unwrap_or(0);
+        let current = visible.iter().position(|&v| v == self.selected.unwrap_or(newest)).unwrap_or(last);
+        self.selected = Some(visible[current.saturating_add_signed(delta).min(last)]);
+    }
+
+    /// Selects the Results row drawn at screen row `y`, given the table's `area` (see
+    /// `results_area`). Returns false for clicks on the borders, the header or empty space.
+    pub fn click_row(&mut self, area: Rect, y: u16) -> bool {
+        // Rows start below the top border and the header.
+        let first_row = area.y + 2;
+        let height = usize::from(area.height.saturating_sub(3));
+        if self.group_by_service || y < first_row || usize::from(y - first_row) >= height {
+            return false;
+        }
+        let visible = self.visible_rows();
+        // The table scrolls just far enough to keep the selection on screen.
+        let offset = self.selected
+            .and_then(|i| visible.iter().position(|&v| v == i))
+            .map_or(0, |selected| (selected + 1).saturating_sub(height));
+        match visible.get(offset + usize::from(y - first_row)) {
+            Some(&idx) => {
+                self.selected = Some(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the first shown row for `port`. Returns false if no shown result has that port.
+    pub fn goto_port(&mut self, port: u16) -> bool {
+        match self.visible_rows().into_iter().find(|&i| self.results[i].port == port) {
+            Some(idx) => {
+                self.selected = Some(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Submits the go-to-port prompt, returning a message for the log.
+    pub fn commit_goto(&mut self) -> Option<String> {
+        let input = self.goto_input.take()?;
+        let msg = match input.trim().parse::<u16>() {
+            Ok(port) if self.goto_port(port) => format!("Jumped to port {}", port),
+            Ok(port) => format!("Port {} is not in the results", port),
+            Err(_) => format!("Not a port number: {}", input.trim()),
+        };
+        Some(msg)
+    }
+
+    pub fn note_for(&self, r: &ScanResult) -> Option<&String> {
+        self.notes.get(&(r.host.clone(), r.port, r.protocol))
+    }
+
+    /// Starts editing the note of the detail result, pre-filled with any existing note.
+    pub fn begin_note(&mut self) -> bool {
+        let Some(r) = self.detail_result() else {
+            return false;
+        };
+        let text = self.note_for(r).cloned().unwrap_or_default();
+        self.note_input = Some(((r.host.clone(), r.port, r.protocol), text));
+        true
+    }
+
+    /// Stores the note being edited; an empty note removes it. Returns the annotated port.
+    pub fn commit_note(&mut self) -> Option<u16> {
+        let (key, text) = self.note_input.take()?;
+        let port = key.1;
+        if text.trim().is_empty() {
+            self.notes.remove(&key);
+        } else {
+            self.notes.insert(key, text.trim().to_string());
+        }
+        Some(port)
+    }
+
+    /// Fills the inputs from profile `idx` and marks it active for the next scan.
+    pub fn select_profile(&mut self, idx: usize) -> Option<&Profile> {
+        let p = self.profiles.get(idx)?;
+        self.host_input = p.host.clone();
+        if let Some(start) = p.start {
+            self.start_port_input = start.to_string();
+        }
+        if let Some(end) = p.end {
+            self.end_port_input = end.to_string();
+        }
+        self.active_profile = Some(idx);
+        self.profiles.get(idx)
+    }
+
+    pub fn next_profile(&mut self) -> Option<&Profile> {
+        if self.profiles.is_empty() {
+            return None;
+        }
+        let idx = self.active_profile.map(|i| (i + 1) % self.profiles.len()).unwrap_or(0);
+        self.select_profile(idx)
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.profiles.get(self.active_profile?)
+    }
+
+    /// Builds a profile from the top bar, named after the target and ports typed.
+    /// Empty or unparsable port and tuning fields are left unset.
+    pub fn profile_from_inputs(&self) -> Option<Profile> {
+        let host = self.host_input.trim();
+        if host.is_empty() {
+            return None;
+        }
+        let start = self.start_port_input.trim().parse::<u16>().ok();
+        let end = self.end_port_input.trim().parse::<u16>().ok();
+        let name = match (start, end) {
+            (Some(s), Some(e)) => format!("{}:{}-{}", host, s, e),
+            _ => host.to_string(),
+        };
+        Some(Profile {
+            name,
+            host: host.to_string(),
+            start,
+            end,
+            concurrency: self.concurrency_input.trim().parse().ok().filter(|&n| n >= 1),
+            timeout_ms: self.timeout_ms_input.trim().parse().ok().filter(|&ms| ms >= MIN_TIMEOUT_MS),
+            ..Default::default()
+        })
+    }
+
+    /// Adds `profile` to the cycle (replacing one with the same name) and makes it active.
+    pub fn add_profile(&mut self, profile: Profile) {
+        let idx = match self.profiles.iter().position(|p| p.name == profile.name) {
+            Some(idx) => {
+                self.profiles[idx] = profile;
+                idx
+            }
+            None => {
+                self.profiles.push(profile);
+                self.profiles.len() - 1
+            }
+        };
+        self.active_profile = Some(idx);
+    }
+
+    /// Current view preferences and notes, for saving on exit.
+    pub fn prefs(&self) -> UiPrefs {
+        let mut notes: Vec<SavedNote> = self
+            .notes
+            .iter()
+            .map(|((host, port, protocol), text)| SavedNote { host: host.clone(), port: *port, protocol: *protocol, text: text.clone() })
+            .collect();
+        notes.sort_by(|a, b| (&a.host, a.port, a.protocol.to_string()).cmp(&(&b.host, b.port, b.protocol.to_string())));
+        UiPrefs {
+            compact: self.compact,
+            group_by_service: self.group_by_service,
+            coalesce_log: self.coalesce_log,
+            show_only_open: self.show_only_open,
+            notes,
+        }
+    }
+
+    pub fn apply_prefs(&mut self, prefs: &UiPrefs) {
+        self.compact = prefs.compact;
+        self.group_by_service = prefs.group_by_service;
+        self.coalesce_log = prefs.coalesce_log;
+        self.show_only_open = prefs.show_only_open;
+        for n in &prefs.notes {
+            self.notes.insert((n.host.clone(), n.port, n.protocol), n.text.clone());
+        }
+    }
+
+    /// Names what keystrokes currently go to, e.g. "EDITING host" or "EDITING note".
+    pub fn mode_label(&self) -> String {
+        if self.note_input.is_some() {
+            return "EDITING note".to_string();
+        }
+        if self.goto_input.is_some() {
+            return "GO TO port".to_string();
+        }
+        if self.filter_editing {
+            return "SEARCH results".to_string();
+        }
+        match self.input_focus {
+            0 => "EDITING host".to_string(),
+            1 => "EDITING start port".to_string(),
+            2 => "EDITING end port".to_string(),
+            3 => "EDITING concurrency".to_string(),
+            4 => "EDITING timeout".to_string(),
+            5 => "EDITING rate".to_string(),
+            6 => "EDITING proxy".to_string(),
+            7 => "EDITING retries".to_string(),
+            _ => "COMMAND".to_string(),
+        }
+    }
+
+    /// Parses the Concurrency, Timeout and Rate fields. Empty fields yield `None`; invalid
+    /// values are logged and also yield `None`, so the scan falls back to its defaults.
+    pub fn tuning(&mut self) -> (Option<usize>, Option<Duration>, Option<u32>) {
+        let concurrency_input = self.concurrency_input.trim().to_string();
+        let concurrency = match concurrency_input.parse::<usize>() {
+            _ if concurrency_input.is_empty() => None,
+            Ok(n) if n >= 1 => Some(n),
+            _ => {
+                self.push_log(format!("Invalid concurrency '{}' (must be at least 1); using default", concurrency_input));
+                None
+            }
+        };
+        let timeout_input = self.timeout_ms_input.trim().to_string();
+        let timeout = match timeout_input.parse::<u64>() {
+            _ if timeout_input.is_empty() => None,
+            Ok(ms) if ms >= MIN_TIMEOUT_MS => Some(Duration::from_millis(ms)),
+            _ => {
+                self.push_log(format!(
+                    "Invalid timeout '{}' (must be at least {}ms); using default",
+                    timeout_input, MIN_TIMEOUT_MS
+                ));
+                None
+            }
+        };
+        let pps_input = self.max_pps_input.trim().to_string();
+        let max_pps = match pps_input.parse::<u32>() {
+            _ if pps_input.is_empty() => None,
+            Ok(n) if n >= 1 => Some(n),
+            _ => {
+                self.push_log(format!("Invalid rate '{}' (must be at least 1 per second); not capping", pps_input));
+                None
+            }
+        };
+        (concurrency, timeout, max_pps)
+    }
+
+    /// Parses the Retries field. Empty yields `None`; values above `MAX_RETRIES` are
+    /// capped and invalid ones ignored, both with a log entry.
+    pub fn retries(&mut self) -> Option<u8> {
+        let input = self.retries_input.trim().to_string();
+        match input.parse::<u64>() {
+            _ if input.is_empty() => None,
+            Ok(n) if n > u64::from(scanner::MAX_RETRIES) => {
+                self.push_log(format!("Retries capped at {} (asked for {})", scanner::MAX_RETRIES, n));
+                Some(scanner::MAX_RETRIES)
+            }
+            Ok(n) => Some(n as u8),
+            Err(_) => {
+                self.push_log(format!("Invalid retries '{}'; using default", input));
+                None
+            }
         }
     }
 
+    /// Parses the Proxy field. Empty means no proxy; anything but an IP `address:port`
+    /// is an error, so a typo never silently scans without the proxy.
+    pub fn proxy(&self) -> Result<Option<SocketAddr>, String> {
+        match self.proxy_input.trim() {
+            "" => Ok(None),
+            text => text.parse().map(Some).map_err(|_| format!("Invalid proxy '{}' (expected address:port, e.g. 127.0.0.1:1080)", text)),
+        }
+    }
+
+    /// Which of the Start and End fields can't be scanned as typed: an unparsable port,
+    /// port 0, Start above End, or an End port list that doesn't parse. Empty fields are
+    /// fine (they default to 1 and 65535).
+    pub fn port_input_errors(&self) -> (bool, bool) {
+        let end_input = self.end_port_input.trim();
+        if end_input.contains([',', '-']) {
+            // A port list in End makes Start irrelevant.
+            return (false, parse_port_spec(end_input).is_err());
+        }
+        let parse = |input: &str, default: u16| match input.trim() {
+            "" => Some(default),
+            text => text.parse::<u16>().ok().filter(|&p| p > 0),
+        };
+        match (parse(&self.start_port_input, 1), parse(end_input, 65535)) {
+            (Some(start), Some(end)) if start > end => (true, true),
+            (start, end) => (start.is_none(), end.is_none()),
+        }
+    }
+
+    pub fn ports_valid(&self) -> bool {
+        self.port_input_errors() == (false, false)
+    }
+
+    /// Types `c` into the focused field. Start takes digits only and End digits, commas
+    /// and dashes (for port lists); other characters are ignored there. Editing the host
+    /// or ports detaches the active profile; the tuning fields don't, since a profile
+    /// never fills them.
     pub fn handle_char_input(&mut self, c: char) {
+        let accepted = match self.input_focus {
+            1 => c.is_ascii_digit(),
+            2 => c.is_ascii_digit() || c == ',' || c == '-',
+            _ => true,
+        };
+        if !accepted {
+            return;
+        }
+        if self.input_focus <= 2 {
+            self.active_profile = None;
+        }
         match self.input_focus {
             0 => self.host_input.push(c),
             1 => self.start_port_input.push(c),
             2 => self.end_port_input.push(c),
+            3 => self.concurrency_input.push(c),
+            4 => self.timeout_ms_input.push(c),
+            5 => self.max_pps_input.push(c),
+            6 => self.proxy_input.push(c),
+            7 => self.retries_input.push(c),
             _ => {}
         }
     }
 
     pub fn handle_backspace(&mut self) {
+        if self.input_focus <= 2 {
+            self.active_profile = None;
+        }
         match self.input_focus {
             0 => { self.host_input.pop(); }
             1 => { self.start_port_input.pop(); }
             2 => { self.end_port_input.pop(); }
+            3 => { self.concurrency_input.pop(); }
+            4 => { self.timeout_ms_input.pop(); }
+            5 => { self.max_pps_input.pop(); }
+            6 => { self.proxy_input.pop(); }
+            7 => { self.retries_input.pop(); }
             _ => {}
         }
     }
 }
 
-pub fn draw(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
+/// Top bar, main area and bottom bar of the frame.
+fn frame_rows(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(6), Constraint::Min(8), Constraint::Length(3)].as_ref())
-        .split(f.size());
+        .split(area)
+}
+
+/// Results column (left) and Detail/Open ports/Log column (right) of the main area.
+fn main_columns(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+        .split(area)
+}
+
+/// The Results table and, when shown, the histogram below it.
+fn results_and_histogram(area: Rect, app: &App) -> (Rect, Option<Rect>) {
+    if !app.show_histogram {
+        return (area, None);
+    }
+    let split = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(HISTOGRAM_HEIGHT)].as_ref())
+        .split(area);
+    (split[0], Some(split[1]))
+}
+
+/// Where `draw` puts the Results table (borders included) in a frame of `area`.
+pub fn results_area(area: Rect, app: &App) -> Rect {
+    results_and_histogram(main_columns(frame_rows(area)[1])[0], app).0
+}
+
+pub fn draw(f: &mut Frame, app: &App) {
+    let chunks = frame_rows(f.area());
 
     draw_top_bar(f, chunks[0], app);
     draw_main(f, chunks[1], app);
     draw_bottom_bar(f, chunks[2], app);
+    if let Some(summary) = &app.last_summary {
+        draw_summary(f, f.area(), summary);
+    }
+    if app.show_help {
+        draw_help(f, f.area());
+    }
+}
+
+/// A `width` × `height` rectangle centred in `area`, shrunk to fit.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+fn draw_help(f: &mut Frame, area: Rect) {
+    let key_width = KEY_HELP.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    let mut lines: Vec<Line> = KEY_HELP.iter()
+        .map(|(key, what)| Line::from(vec![
+            Span::styled(format!("{:<width$}  ", key, width = key_width), Style::default().fg(Color::Yellow)),
+            Span::raw(*what),
+        ]))
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Press any key to close", Style::default().fg(Color::DarkGray))));
+    let width = KEY_HELP.iter().map(|(_, w)| w.len()).max().unwrap_or(0) + key_width + 6;
+    let popup = centered(area, width as u16, lines.len() as u16 + 2);
+    f.render_widget(Clear, popup);
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Help")), popup);
+}
+
+/// Most open ports listed by the summary popup; the rest are counted.
+const SUMMARY_MAX_OPEN: usize = 12;
+
+fn draw_summary(f: &mut Frame, area: Rect, summary: &ScanSummary) {
+    let label = Style::default().fg(Color::Yellow);
+    let field = |name: &str, value: String| Line::from(vec![Span::styled(format!("{:<14}", name), label), Span::raw(value)]);
+    let target = match summary.scope.as_str() {
+        "" => summary.host.clone(),
+        scope => format!("{} ({})", summary.host, scope),
+    };
+    let mut counts = format!("{} open, {} closed, {} filtered", summary.open, summary.closed, summary.filtered);
+    if summary.other > 0 {
+        counts.push_str(&format!(", {} other", summary.other));
+    }
+    let mut lines = vec![
+        field("Target", target),
+        field("Duration", format!("{:.2}s", summary.duration.as_secs_f64())),
+        field("Scanned", summary.scanned.to_string()),
+        field("Results", counts),
+        field("Avg response", summary.avg_response_ms.map_or("-".to_string(), |ms| format!("{}ms", ms))),
+        Line::from(""),
+        Line::from(Span::styled("Open ports", label)),
+    ];
+    if summary.open_ports.is_empty() {
+        lines.push(Line::from("  None"));
+    }
+    lines.extend(summary.open_ports.iter().take(SUMMARY_MAX_OPEN).map(|p| Line::from(format!("  {}", p))));
+    if summary.open_ports.len() > SUMMARY_MAX_OPEN {
+        lines.push(Line::from(format!("  ... and {} more", summary.open_ports.len() - SUMMARY_MAX_OPEN)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Press any key to close", Style::default().fg(Color::DarkGray))));
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) + 4;
+    let popup = centered(area, width as u16, lines.len() as u16 + 2);
+    f.render_widget(Clear, popup);
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Scan summary")), popup);
 }
 
 fn draw_top_bar(f: &mut Frame, area: Rect, app: &App) {
@@ -96,56 +1283,196 @@ fn draw_top_bar(f: &mut Frame, area: Rect, app: &App) {
         app.host_input.clone()
     };
 
-    let left = format!("Target: {}", host_display);
-    let mid = if app.is_scanning {
-        match app.started_at {
-            Some(t0) => format!("Status: LIVE | Elapsed: {:.1}s", t0.elapsed().as_secs_f64()),
-            None => "Status: LIVE".to_string(),
+    let left = match app.active_profile() {
+        Some(p) => format!("Target: {}  [profile: {}]", host_display, p.name),
+        None => format!("Target: {}", host_display),
+    };
+    let status = if app.is_scanning {
+        let state = if app.is_paused { "PAUSED" } else { "LIVE" };
+        match app.scan_elapsed() {
+            Some(elapsed) => format!("Status: {} | Elapsed: {:.1}s", state, elapsed.as_secs_f64()),
+            None => format!("Status: {}", state),
         }
     } else {
         "Status: IDLE".to_string()
     };
-    let right = format!("Open: {}  Scanned: {}", app.results.iter().filter(|r| r.status=="open").count(), app.total_scanned);
+    let status = match app.scan_error {
+        Some(_) if !app.is_scanning => Line::from(Span::styled("Status: FAILED", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+        _ => Line::from(status),
+    };
+    let mode_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mid = vec![status, Line::from(Span::styled(format!("Mode: {}", app.mode_label()), mode_style))];
+    let rate = app.scan_rate().map_or("-".to_string(), |r| format!("{:.0}/s", r));
+    let latency = app.latency.min_avg_max().map_or("-".to_string(), |(min, avg, max)| format!("{}/{}/{}ms", min, avg, max));
+    let right = format!(
+        "Open: {}  Scanned: {}\nServices: {}  Rate: {}\nResp min/avg/max:\n{}",
+        app.results.iter().filter(|r| r.status=="open").count(),
+        app.total_scanned,
+        app.distinct_services(),
+        rate,
+        latency
+    );
 
     let row = Layout::default().direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(30), Constraint::Percentage(20)].as_ref())
         .split(area);
+    let field_rows = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(3)].as_ref())
+        .split(row[0]);
+    let target_fields = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Min(20), Constraint::Length(9), Constraint::Length(9)].as_ref())
+        .split(field_rows[0]);
+    let tuning_fields = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Length(13), Constraint::Length(13), Constraint::Length(11), Constraint::Min(10), Constraint::Length(9)].as_ref())
+        .split(field_rows[1]);
+    let fields = [target_fields[0], target_fields[1], target_fields[2], tuning_fields[0], tuning_fields[1], tuning_fields[2], tuning_fields[3], tuning_fields[4]];
+
+    // The focused input gets a highlighted border; none is highlighted while a prompt is open.
+    let prompt_open = app.note_input.is_some() || app.goto_input.is_some() || app.filter_editing;
+    let field_block = |title: &'static str, idx: usize| {
+        let block = Block::default().borders(Borders::ALL).title(title);
+        if !prompt_open && app.input_focus == idx {
+            block.border_style(mode_style).title_style(mode_style)
+        } else {
+            block
+        }
+    };
 
-    f.render_widget(Paragraph::new(left).block(Block::default().borders(Borders::ALL).title("Target")), row[0]);
-    f.render_widget(Paragraph::new(mid).block(Block::default().borders(Borders::ALL).title("Status")), row[1]);
+    // Port fields that can't be scanned as typed are shown in red.
+    let (start_bad, end_bad) = app.port_input_errors();
+    let port_field = |text: &str, title: &'static str, idx: usize, bad: bool| {
+        let block = field_block(title, idx);
+        match bad {
+            true => Paragraph::new(text.to_string()).style(Style::default().fg(Color::Red)).block(block.border_style(Style::default().fg(Color::Red))),
+            false => Paragraph::new(text.to_string()).block(block),
+        }
+    };
+    f.render_widget(Paragraph::new(left).block(field_block("Target", 0)), fields[0]);
+    f.render_widget(port_field(&app.start_port_input, "Start", 1, start_bad), fields[1]);
+    f.render_widget(port_field(&app.end_port_input, "End", 2, end_bad), fields[2]);
+    f.render_widget(Paragraph::new(app.concurrency_input.as_str()).block(field_block("Concurrency", 3)), fields[3]);
+    f.render_widget(Paragraph::new(app.timeout_ms_input.as_str()).block(field_block("Timeout(ms)", 4)), fields[4]);
+    f.render_widget(Paragraph::new(app.max_pps_input.as_str()).block(field_block("Rate(pps)", 5)), fields[5]);
+    f.render_widget(Paragraph::new(app.proxy_input.as_str()).block(field_block("Proxy", 6)), fields[6]);
+    f.render_widget(Paragraph::new(app.retries_input.as_str()).block(field_block("Retries", 7)), fields[7]);
+    let status_block = Block::default().borders(Borders::ALL).title("Status");
+    let status_rows = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(1)].as_ref())
+        .split(status_block.inner(row[1]));
+    f.render_widget(status_block, row[1]);
+    f.render_widget(Paragraph::new(mid), status_rows[0]);
+    let ratio = app.progress();
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(Color::Green))
+        .ratio(ratio)
+        .label(if app.is_scanning {
+            let eta = app.eta().map_or("--".to_string(), |d| format!("{}s", d.as_secs_f64().ceil() as u64));
+            format!("{:.0}%  ETA: {}", ratio * 100.0, eta)
+        } else {
+            format!("{:.0}%", ratio * 100.0)
+        });
+    f.render_widget(gauge, status_rows[1]);
     f.render_widget(Paragraph::new(right).block(Block::default().borders(Borders::ALL).title("Counters")), row[2]);
 }
 
 fn draw_main(f: &mut Frame, area: Rect, app: &App) {
-    let cols = Layout::default().direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
-        .split(area);
+    let cols = main_columns(area);
 
-    let header = Row::new(vec!["Port", "State", "Service", "Resp(ms)"]).style(Style::default().add_modifier(Modifier::BOLD));
-    let rows = app.results.iter().map(|r| {
-        let color = match r.status.as_str() {
-            "open" => Color::Green,
-            "closed" => Color::Gray,
-            "timeout" => Color::Yellow,
-            _ => Color::White,
+    let color_of = |r: &ScanResult| if app.is_stale(r) { Color::DarkGray } else { app.theme.status_color(&r.status) };
+    let (header, widths): (Vec<&str>, Vec<Constraint>) = if app.compact {
+        (vec!["Port", "State"], vec![Constraint::Length(12), Constraint::Length(14)])
+    } else {
+        let mut header = vec!["Port", "Proto", "State", "Service", "Resp(ms)", "Version"];
+        let mut widths = vec![Constraint::Length(8), Constraint::Length(6), Constraint::Length(14), Constraint::Length(16), Constraint::Length(10), Constraint::Min(10)];
+        if app.multi_target() {
+            header.insert(0, "Address");
+            widths.insert(0, Constraint::Length(18));
+        }
+        (header, widths)
+    };
+    let header = Row::new(header).style(Style::default().add_modifier(Modifier::BOLD));
+    let visible = app.visible_rows();
+    let rows = visible.iter().map(|&i| &app.results[i]).map(|r| {
+        let cells = if app.compact {
+            vec![format!("{}/{}", r.port, r.protocol), r.status.clone()]
+        } else {
+            let mut cells = vec![
+                r.port.to_string(),
+                r.protocol.to_string(),
+                r.status.clone(),
+                r.service.clone(),
+                r.response_ms.to_string(),
+                r.software.as_ref().map(ToString::to_string).unwrap_or_default(),
+            ];
+            if app.multi_target() {
+                cells.insert(0, r.host.clone());
+            }
+            cells
         };
-        Row::new(vec![
-            r.port.to_string(),
-            r.status.clone(),
-            r.service.clone(),
-            r.response_ms.to_string(),
-        ]).style(Style::default().fg(color))
+        Row::new(cells).style(Style::default().fg(color_of(r)))
     });
 
-    let table = Table::new(rows, [Constraint::Length(8), Constraint::Length(10), Constraint::Length(16), Constraint::Length(10)])
+    let mut tags = Vec::new();
+    if app.compact {
+        tags.push("compact".to_string());
+    }
+    if app.show_only_open {
+        tags.push("open only".to_string());
+    }
+    if app.sort_by != SortBy::Arrival || app.sort_desc {
+        tags.push(format!("by {} {}", app.sort_by.label(), if app.sort_desc { "↓" } else { "↑" }));
+    }
+    if !app.filter_input.is_empty() && !app.filter_editing {
+        tags.push(format!("matching \"{}\"", app.filter_input));
+    }
+    let title = if tags.is_empty() { "Results".to_string() } else { format!("Results ({})", tags.join(", ")) };
+    let title = match &app.goto_input {
+        Some(input) => format!("{} | Go to port: {}_", title, input),
+        None if app.filter_editing => format!("{} | Search: {}_", title, app.filter_input),
+        None => title,
+    };
+    let table = Table::new(rows, widths)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Results"));
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(title));
 
-    f.render_widget(table, cols[0]);
+    let (results_area, histogram_area) = results_and_histogram(cols[0], app);
+    if let Some(area) = histogram_area {
+        draw_histogram(f, area, app);
+    }
+    if let Some(e) = app.scan_error.as_ref().filter(|_| app.results.is_empty()) {
+        let failure = Paragraph::new(format!("Scan failed: {}", e))
+            .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL).title("Results"));
+        f.render_widget(failure, results_area);
+    } else if app.group_by_service {
+        draw_service_groups(f, results_area, app);
+    } else {
+        let selected = app.selected.and_then(|i| visible.iter().position(|&v| v == i));
+        let mut state = TableState::default().with_selected(selected);
+        f.render_stateful_widget(table, results_area, &mut state);
+    }
 
     let mut detail = String::new();
-    if let Some(r) = app.results.last() {
-        detail.push_str(&format!("Port: {}\nState: {}\nService: {}\nResp: {}ms\n\n", r.port, r.status, r.service, r.response_ms));
+    if let Some(r) = app.detail_result() {
+        let mut via = r.family.map(|fam| format!(" via {}", fam)).unwrap_or_default();
+        if app.multi_target() {
+            via.push_str(&format!(" on {}", r.host));
+        }
+        let slow = if r.read_timed_out { " (no greeting, read timed out)" } else { "" };
+        let version = r.software.as_ref().map(|v| format!(" ({})", v)).unwrap_or_default();
+        let tries = if r.attempts > 1 { format!(" ({} attempts)", r.attempts) } else { String::new() };
+        detail.push_str(&format!("Port: {}/{}{}\nState: {}{}\nService: {}{}\nResp: {}ms{}\n", r.port, r.protocol, via, r.status, slow, r.service, version, r.response_ms, tries));
+        if let Some(e) = r.error.as_ref().filter(|_| app.verbose || r.status == "error") {
+            detail.push_str(&format!("Error: {}\n", e));
+        }
+        if let Some(((_, port, _), input)) = &app.note_input {
+            detail.push_str(&format!("Note for {}: {}_\n", port, input));
+        } else if let Some(note) = app.note_for(r) {
+            detail.push_str(&format!("Note: {}\n", note));
+        }
+        detail.push('\n');
         if let Some(b) = &r.banner {
             detail.push_str(&format!("Banner:\n{}\n", b));
         }
@@ -153,26 +1480,81 @@ fn draw_main(f: &mut Frame, area: Rect, app: &App) {
         detail.push_str("No selection\n");
     }
 
-    let log_text = if app.log_events.is_empty() {
-        "No events".to_string()
+
+    let open = app.open_ports();
+    let open_text = if open.is_empty() {
+        "None yet".to_string()
     } else {
-        let start = if app.log_events.len() > 100 { app.log_events.len() - 100 } else { 0 };
-        app.log_events[start..].join("\n")
+        open.iter().map(|r| match app.multi_target() {
+            true => format!("{} {}/{} {}", r.host, r.port, r.protocol, r.service),
+            false => format!("{:>5}/{} {}", r.port, r.protocol, r.service),
+        }).collect::<Vec<_>>().join("\n")
     };
 
     let right_chunks = Layout::default().direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Min(4)].as_ref())
+        .constraints([Constraint::Length(8), Constraint::Percentage(40), Constraint::Min(4)].as_ref())
         .split(cols[1]);
 
     f.render_widget(Paragraph::new(detail).block(Block::default().borders(Borders::ALL).title("Detail")), right_chunks[0]);
-    f.render_widget(Paragraph::new(log_text).block(Block::default().borders(Borders::ALL).title("Log")), right_chunks[1]);
+    f.render_widget(Paragraph::new(open_text).block(Block::default().borders(Borders::ALL).title(format!("Open ports ({})", open.len()))), right_chunks[1]);
+    // The newest events that fit, ending `log_scroll` events before the last one.
+    let log_height = usize::from(right_chunks[2].height.saturating_sub(2));
+    let end = app.log_events.len() - app.log_scroll.min(app.log_events.len());
+    let log_text = if app.log_events.is_empty() {
+        "No events".to_string()
+    } else {
+        app.log_events[end.saturating_sub(log_height)..end].join("\n")
+    };
+    let log_title = if app.log_scroll > 0 { "Log [scrolled back]" } else { "Log" };
+    f.render_widget(Paragraph::new(log_text).block(Block::default().borders(Borders::ALL).title(log_title)), right_chunks[2]);
 }
 
-fn draw_bottom_bar(f: &mut Frame, area: Rect, _app: &App) {
+/// Rows taken by the histogram below the Results table, borders included.
+const HISTOGRAM_HEIGHT: u16 = 9;
+
+/// Bars of open and closed response times per `stats::HISTOGRAM_BUCKETS` range.
+fn draw_histogram(f: &mut Frame, area: Rect, app: &App) {
+    let data = stats::response_histogram(&app.results);
+    let chart = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title("Response times (ms, open + closed)"))
+        .data(&data)
+        .bar_width(7)
+        .bar_gap(2)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+    f.render_widget(chart, area);
+}
+
+fn draw_service_groups(f: &mut Frame, area: Rect, app: &App) {
+    let groups = app.service_groups();
+    let lines: Vec<Line> = if groups.is_empty() {
+        vec![Line::from("No open ports yet")]
+    } else {
+        groups.iter().map(|(service, rs)| {
+            let noun = if rs.len() == 1 { "endpoint" } else { "endpoints" };
+            let ports = rs.iter().map(|r| format!("{}/{}", r.port, r.protocol)).collect::<Vec<_>>().join(", ");
+            Line::from(vec![
+                Span::styled(format!("{}: {} {}", service, rs.len(), noun), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  {}", ports)),
+            ])
+        }).collect()
+    };
+    let title = format!("By service ({})", app.scan_host);
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(title)), area);
+}
+
+fn draw_bottom_bar(f: &mut Frame, area: Rect, app: &App) {
     let chunks = Layout::default().direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(20), Constraint::Percentage(60), Constraint::Percentage(20)].as_ref())
         .split(area);
 
+    let legend: Vec<Span> = app.theme.legend().into_iter()
+        .flat_map(|(label, color)| [Span::styled("■", Style::default().fg(color)), Span::raw(format!("{} ", label))])
+        .collect();
+    f.render_widget(Paragraph::new(Line::from(legend))
+        .block(Block::default().borders(Borders::ALL).title("Legend")), chunks[0]);
+
     f.render_widget(Paragraph::new("F1: Help  S/Enter: Start  T: TopScan  C: Cancel  Q: Quit")
         .block(Block::default().borders(Borders::ALL).title("Controls")), chunks[1]);
 }
\ No newline at end of file
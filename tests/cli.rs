@@ -0,0 +1,56 @@
+use clap::Parser;
+use night_tool::cli::Cli;
+use night_tool::scanner::{scan_range, Ramp, ScanConfig, ScanEvent};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(std::iter::once("night_tool").chain(args.iter().copied()))
+}
+
+#[test]
+fn ramp_flags_build_the_ramp_schedule() {
+    let cli = parse(&["--ramp-step", "8", "--ramp-interval-ms", "250"]).unwrap();
+    assert_eq!(cli.scan_config().ramp, Some(Ramp { step: 8, interval: Duration::from_millis(250) }));
+
+    // The interval defaults to a second; a step of 0 turns ramping off.
+    let cli = parse(&["--ramp-step", "8"]).unwrap();
+    assert_eq!(cli.scan_config().ramp.map(|r| r.interval), Some(Duration::from_secs(1)));
+    assert_eq!(parse(&["--ramp-step", "0"]).unwrap().scan_config().ramp, None);
+    assert_eq!(parse(&[]).unwrap().scan_config().ramp, None);
+
+    assert!(parse(&["--ramp-interval-ms", "250"]).is_err(), "the interval needs a step");
+}
+
+/// Scans one listening port with `config` and returns every trace line it produced.
+async fn traced_scan(mut config: ScanConfig, mut trace: mpsc::Receiver<String>) -> Vec<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+    config.banner_timeout = Some(Duration::from_millis(50));
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(16);
+    scan_range("127.0.0.1", port, port, &config, tx).await;
+    while rx.recv().await.is_some() {}
+    // Dropping the config closes the trace channel, so reading it ends.
+    drop(config);
+    let mut lines = Vec::new();
+    while let Some(line) = trace.recv().await {
+        lines.push(line);
+    }
+    lines
+}
+
+#[tokio::test]
+async fn connection_traces_are_emitted_only_in_verbose_mode() {
+    let cli = parse(&["--verbose"]).unwrap();
+    let mut config = cli.scan_config();
+    let trace = cli.attach_trace(&mut config).expect("--verbose attaches a trace sink");
+    let lines = traced_scan(config, trace).await;
+    assert!(lines.iter().any(|l| l.contains("/tcp attempt 1: open in ")), "{:?}", lines);
+
+    let cli = parse(&[]).unwrap();
+    let mut config = cli.scan_config();
+    assert!(cli.attach_trace(&mut config).is_none());
+    assert!(config.trace.is_none(), "quiet scans get no trace sink");
+}
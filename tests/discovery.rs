@@ -0,0 +1,75 @@
+use night_tool::discovery::{ping_host, ping_with, PingMethod};
+use night_tool::scanner::{scan_range, ScanConfig, ScanEvent};
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const LOOPBACK: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+/// IPv6 documentation prefix (RFC 3849): never routed, so nothing answers.
+const DEAD: IpAddr = IpAddr::V6(std::net::Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+#[tokio::test]
+async fn loopback_answers_a_ping() {
+    assert!(ping_host(LOOPBACK, Duration::from_secs(1)).await);
+    // Refused connections count as alive, so this holds whether or not 80/443 listen.
+    assert!(ping_with(PingMethod::Tcp, LOOPBACK, Duration::from_secs(1)).await);
+}
+
+#[tokio::test]
+async fn unrouted_address_does_not_answer() {
+    assert!(!ping_host(DEAD, Duration::from_millis(300)).await);
+}
+
+#[tokio::test]
+async fn dead_hosts_are_skipped() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((_stream, _)) = listener.accept().await {}
+    });
+    let config = ScanConfig {
+        addresses: vec![LOOPBACK, DEAD],
+        skip_dead_hosts: true,
+        timeout: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(16);
+    scan_range("unused", port, port, &config, tx).await;
+
+    let mut hosts = Vec::new();
+    while let Some(event) = rx.recv().await {
+        let r = match event {
+            ScanEvent::Result(r) => r,
+            ScanEvent::Finished { .. } => break,
+            _ => continue,
+        };
+        hosts.push((r.host, r.status));
+    }
+    assert_eq!(hosts, vec![("127.0.0.1".to_string(), "open".to_string())]);
+}
+
+#[tokio::test]
+async fn deadline_covers_host_discovery() {
+    let config = ScanConfig {
+        addresses: vec![DEAD],
+        skip_dead_hosts: true,
+        timeout: Some(Duration::from_secs(10)),
+        deadline: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(16);
+    let t0 = std::time::Instant::now();
+    scan_range("unused", 1, 10, &config, tx).await;
+    assert!(t0.elapsed() < Duration::from_secs(3), "took {:?}", t0.elapsed());
+
+    let mut finish = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            ScanEvent::Result(r) => panic!("unexpected result {:?}", r),
+            ScanEvent::Finished { timed_out, .. } => finish = Some(timed_out),
+            _ => {}
+        }
+    }
+    assert_eq!(finish, Some(true));
+}
@@ -0,0 +1,196 @@
+use night_tool::export::{export_csv, export_json, export_nmap_xml, write_audit_log, write_tsv, LiveJsonl, Notes};
+use night_tool::scanner::{Protocol, ScanEvent, ScanResult};
+use night_tool::services::Software;
+use night_tool::ui::App;
+use tokio::sync::mpsc;
+
+fn result(port: u16, status: &str, banner: Option<&str>) -> ScanResult {
+    ScanResult {
+        host: "127.0.0.1".to_string(),
+        port,
+        protocol: Protocol::Tcp,
+        status: status.to_string(),
+        service: "ssh".to_string(),
+        response_ms: 4,
+        banner: banner.map(str::to_string),
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: None,
+    }
+}
+
+#[test]
+fn json_export_writes_one_object_per_result() {
+    let path = std::env::temp_dir().join(format!("night_tool_export_{}.json", std::process::id()));
+    let results = [result(22, "open", Some("SSH-2.0-OpenSSH_9.6")), result(23, "closed", None)];
+    let notes = Notes::from([(("127.0.0.1".to_string(), 22, Protocol::Tcp), "outdated version".to_string())]);
+    export_json(&results, &notes, &path).unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let rows = value.as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["port"], 22);
+    assert_eq!(rows[0]["protocol"], "tcp");
+    assert_eq!(rows[0]["status"], "open");
+    assert_eq!(rows[0]["service"], "ssh");
+    assert_eq!(rows[0]["response_ms"], 4);
+    assert_eq!(rows[0]["banner"], "SSH-2.0-OpenSSH_9.6");
+    assert_eq!(rows[0]["note"], "outdated version");
+    assert!(rows[1].get("note").is_none(), "results without a note have no note field");
+}
+
+#[test]
+fn csv_export_quotes_banners_and_keeps_one_line_per_record() {
+    let path = std::env::temp_dir().join(format!("night_tool_export_{}.csv", std::process::id()));
+    let results = [result(22, "open", Some("220 \"mail\", ready\r\n220 more"))];
+    export_csv(&results, &Notes::new(), &path).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines, ["host,port,protocol,status,service,product,version,response_ms,banner,note", "127.0.0.1,22,tcp,open,ssh,,,4,\"220 \"\"mail\"\", ready 220 more\","]);
+}
+
+#[test]
+fn notes_follow_host_port_and_protocol_into_csv_and_tsv() {
+    let path = std::env::temp_dir().join(format!("night_tool_notes_{}.csv", std::process::id()));
+    let mut udp = result(53, "open", None);
+    udp.protocol = Protocol::Udp;
+    let results = [result(53, "open", None), udp];
+    let notes = Notes::from([(("127.0.0.1".to_string(), 53, Protocol::Udp), "open resolver, check recursion".to_string())]);
+    export_csv(&results, &notes, &path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let csv: Vec<&str> = csv.lines().collect();
+    assert_eq!(csv[1..], ["127.0.0.1,53,tcp,open,ssh,,,4,,", "127.0.0.1,53,udp,open,ssh,,,4,,\"open resolver, check recursion\""]);
+
+    let mut tsv = Vec::new();
+    write_tsv(&results, &notes, &mut tsv).unwrap();
+    let tsv = String::from_utf8(tsv).unwrap();
+    let tsv: Vec<&str> = tsv.lines().collect();
+    assert!(tsv[0].ends_with("\tproduct\tversion\tresponse_ms\tbanner\tnote"));
+    assert!(tsv[1].ends_with("\t\t"), "the TCP row has no note: {:?}", tsv[1]);
+    assert!(tsv[2].ends_with("\topen resolver, check recursion"));
+}
+
+#[test]
+fn exports_carry_the_detected_product_and_version_as_separate_fields() {
+    let path = std::env::temp_dir().join(format!("night_tool_version_{}.csv", std::process::id()));
+    let mut ssh = result(22, "open", Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3"));
+    ssh.software = Some(Software { product: "OpenSSH".to_string(), version: "8.9p1".to_string() });
+    let results = [ssh];
+    export_csv(&results, &Notes::new(), &path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(csv.lines().nth(1), Some("127.0.0.1,22,tcp,open,ssh,OpenSSH,8.9p1,4,SSH-2.0-OpenSSH_8.9p1 Ubuntu-3,"));
+
+    let mut tsv = Vec::new();
+    write_tsv(&results, &Notes::new(), &mut tsv).unwrap();
+    let tsv = String::from_utf8(tsv).unwrap();
+    assert_eq!(tsv.lines().nth(1), Some("127.0.0.1\t22\ttcp\topen\tssh\tOpenSSH\t8.9p1\t4\tSSH-2.0-OpenSSH_8.9p1 Ubuntu-3\t"));
+
+    let json = serde_json::to_value(&results[0]).unwrap();
+    assert_eq!(json["software"]["product"], "OpenSSH");
+    assert_eq!(json["software"]["version"], "8.9p1");
+}
+
+#[test]
+fn live_jsonl_appends_each_result_as_it_is_written() {
+    let path = std::env::temp_dir().join(format!("night_tool_live_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut live = LiveJsonl::open(&path).unwrap();
+    live.write(&result(22, "open", Some("SSH-2.0-OpenSSH_9.6"))).unwrap();
+    // Readable before the writer is closed.
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+    drop(live);
+
+    // A second scan appends rather than truncating.
+    let mut live = LiveJsonl::open(&path).unwrap();
+    live.write(&result(23, "closed", None)).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let ports: Vec<u64> = text.lines().map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["port"].as_u64().unwrap()).collect();
+    assert_eq!(ports, vec![22, 23]);
+}
+
+/// Value of attribute `name` on the first `<tag ...>` element of `xml`.
+fn xml_attr<'a>(xml: &'a str, tag: &str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{} ", tag))?;
+    let element = &xml[start..start + xml[start..].find('>')?];
+    let value = &element[element.find(&format!(" {}=\"", name))? + name.len() + 3..];
+    Some(&value[..value.find('"')?])
+}
+
+#[test]
+fn nmap_xml_records_scan_times_and_only_nmap_port_states() {
+    let path = std::env::temp_dir().join(format!("night_tool_export_{}.xml", std::process::id()));
+    let started = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    let finished = started + std::time::Duration::from_secs(42);
+    let results = ["open", "closed", "reset", "filtered", "unreachable", "open|filtered", "error"]
+        .iter()
+        .enumerate()
+        .map(|(i, status)| result(20 + i as u16, status, None))
+        .collect::<Vec<_>>();
+    export_nmap_xml(&results, "127.0.0.1", started, finished, &path).unwrap();
+    let xml = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(xml_attr(&xml, "nmaprun", "start"), Some("1700000000"));
+    assert_eq!(xml_attr(&xml, "nmaprun", "end"), Some("1700000042"));
+    assert_eq!(xml_attr(&xml, "finished", "elapsed"), Some("42"));
+    let states: Vec<&str> = xml.split("<state state=\"").skip(1).map(|s| &s[..s.find('"').unwrap()]).collect();
+    assert_eq!(states, ["open", "closed", "closed", "filtered", "filtered", "open|filtered", "filtered"]);
+}
+
+/// Re-derives the audit chain (FNV-1a over the previous checksum and the line) and
+/// returns the index of the first line whose checksum does not match.
+fn first_broken_audit_line(log: &str) -> Option<usize> {
+    let mut chain: u64 = 0;
+    for (i, entry) in log.lines().enumerate() {
+        let (sum, line) = entry.split_once(' ').unwrap();
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for b in chain.to_be_bytes().iter().chain(line.as_bytes()) {
+            hash ^= u64::from(*b);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        if u64::from_str_radix(sum, 16).ok() != Some(hash) {
+            return Some(i);
+        }
+        chain = hash;
+    }
+    None
+}
+
+#[test]
+fn audit_log_checksums_chain_over_previous_lines() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.operator = Some("tester".to_string());
+    app.clear_log();
+    app.start_job("10.0.0.5", "ports 1-100");
+    app.finish_job("completed");
+    app.push_log("Scan started: 10.0.0.5:1-100");
+    app.push_log("Scan finished in 1.00s");
+    let mut out = Vec::new();
+    write_audit_log(&app, &mut out).unwrap();
+    let log = String::from_utf8(out).unwrap();
+    assert_eq!(first_broken_audit_line(&log), None);
+
+    let lines: Vec<&str> = log.lines().collect();
+    let job = lines.iter().position(|l| l.contains(" job 1 ")).unwrap();
+    // Rewriting one line breaks its checksum...
+    let mut edited = lines.clone();
+    let forged = lines[job].replace("outcome=completed", "outcome=cancelled");
+    edited[job] = &forged;
+    assert_eq!(first_broken_audit_line(&edited.join("\n")), Some(job));
+    // ...and deleting it breaks the line that followed, whose checksum covered it.
+    let mut deleted = lines.clone();
+    deleted.remove(job);
+    assert_eq!(first_broken_audit_line(&deleted.join("\n")), Some(job));
+}
@@ -0,0 +1,57 @@
+use night_tool::scanner::{scan_ports, Protocol, ScanConfig, ScanEvent};
+use night_tool::services::{set_services, ServiceMap};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const RESPONSE: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\nServer: tiny/1.0\r\n\r\n<html><head><TITLE>\n  Router  admin </TITLE></head></html>";
+
+/// Answers one HTTP request per connection, and nothing before a request arrives.
+async fn http_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 512];
+                if let Ok(n) = stream.read(&mut buf).await && buf[..n].starts_with(b"GET / HTTP/1.0\r\n") {
+                    let _ = stream.write_all(RESPONSE).await;
+                }
+            });
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn web_ports_are_probed_unless_denied() {
+    let (probed, denied) = (http_server().await, http_server().await);
+    let mut names = ServiceMap::new();
+    names.insert((probed, Protocol::Tcp), "http".to_string());
+    names.insert((denied, Protocol::Tcp), "http".to_string());
+    assert!(set_services(names));
+
+    let config = ScanConfig {
+        probe_deny: vec![denied],
+        banner_timeout: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(8);
+    scan_ports("127.0.0.1", vec![probed, denied], &config, tx).await;
+
+    let mut results = Vec::new();
+    while let Some(event) = rx.recv().await {
+        let r = match event {
+            ScanEvent::Result(r) => r,
+            ScanEvent::Finished { .. } => break,
+            _ => continue,
+        };
+        results.push(r);
+    }
+    let by_port = |port| results.iter().find(|r| r.port == port).unwrap();
+
+    assert_eq!(by_port(probed).banner.as_deref(), Some("HTTP/1.0 200 OK | Server: tiny/1.0 | Title: Router admin"));
+    assert_eq!(by_port(denied).banner, None);
+    assert!(by_port(denied).read_timed_out);
+}
@@ -0,0 +1,37 @@
+use night_tool::ports::{format_port_spec, parse_port_spec, PortSpecError};
+
+#[test]
+fn repeated_ports_are_scanned_once() {
+    assert_eq!(parse_port_spec("80,80,8080").unwrap(), vec![80, 8080]);
+}
+
+#[test]
+fn list_overlapping_a_range_collapses() {
+    assert_eq!(parse_port_spec("22,20-25,25,443").unwrap(), vec![20, 21, 22, 23, 24, 25, 443]);
+}
+
+#[test]
+fn overlapping_ranges_collapse_and_sort() {
+    assert_eq!(parse_port_spec("8-10, 1-3, 2-9").unwrap(), (1..=10).collect::<Vec<u16>>());
+}
+
+#[test]
+fn whitespace_and_empty_tokens_are_ignored() {
+    assert_eq!(parse_port_spec(" 443 , ,22 ").unwrap(), vec![22, 443]);
+}
+
+#[test]
+fn invalid_tokens_are_rejected() {
+    assert_eq!(parse_port_spec("22,http"), Err(PortSpecError::InvalidPort("http".to_string())));
+    assert_eq!(parse_port_spec("0"), Err(PortSpecError::InvalidPort("0".to_string())));
+    assert_eq!(parse_port_spec("70000"), Err(PortSpecError::InvalidPort("70000".to_string())));
+    assert_eq!(parse_port_spec("30-20"), Err(PortSpecError::InvalidRange("30-20".to_string())));
+    assert_eq!(parse_port_spec(" , "), Err(PortSpecError::Empty));
+}
+
+#[test]
+fn formatted_spec_round_trips() {
+    let ports = parse_port_spec("443,20-25,80,81").unwrap();
+    assert_eq!(format_port_spec(&ports), "20-25,80-81,443");
+    assert_eq!(parse_port_spec(&format_port_spec(&ports)).unwrap(), ports);
+}
@@ -0,0 +1,60 @@
+use night_tool::config::{load_profiles, save_profile, Profile, UiPrefs};
+use night_tool::scanner::{Protocol, ScanEvent};
+use night_tool::ui::App;
+use tokio::sync::mpsc;
+
+#[test]
+fn saving_a_profile_replaces_one_with_the_same_name() {
+    let path = std::env::temp_dir().join(format!("night_tool_profiles_{}.toml", std::process::id()));
+    assert!(load_profiles(&path).unwrap().is_empty());
+
+    let mut web = Profile { name: "web".into(), host: "10.0.0.5".into(), start: Some(80), end: Some(443), ..Default::default() };
+    save_profile(&path, &web).unwrap();
+    save_profile(&path, &Profile { name: "dns".into(), host: "10.0.0.53".into(), ..Default::default() }).unwrap();
+    web.timeout_ms = Some(5000);
+    save_profile(&path, &web).unwrap();
+
+    let loaded = load_profiles(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0], web);
+    assert_eq!(loaded[1].name, "dns");
+}
+
+#[test]
+fn profile_is_built_from_the_top_bar() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.profile_from_inputs(), None);
+
+    app.host_input = "10.0.0.5".to_string();
+    app.start_port_input = "80".to_string();
+    app.end_port_input = "8443".to_string();
+    app.concurrency_input = "64".to_string();
+    app.timeout_ms_input = "10".to_string();
+    let profile = app.profile_from_inputs().unwrap();
+    assert_eq!(profile.name, "10.0.0.5:80-8443");
+    assert_eq!((profile.start, profile.end, profile.concurrency, profile.timeout_ms), (Some(80), Some(8443), Some(64), None));
+
+    app.add_profile(profile.clone());
+    app.add_profile(profile);
+    assert_eq!(app.profiles.len(), 1);
+    assert_eq!(app.active_profile().map(|p| p.name.as_str()), Some("10.0.0.5:80-8443"));
+}
+
+#[test]
+fn notes_survive_saving_and_loading_the_state_file() {
+    let path = std::env::temp_dir().join(format!("night_tool_state_notes_{}.toml", std::process::id()));
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.notes.insert(("10.0.0.5".to_string(), 53, Protocol::Tcp), "zone transfer allowed".to_string());
+    app.notes.insert(("10.0.0.5".to_string(), 53, Protocol::Udp), "open resolver".to_string());
+    app.prefs().save(&path).unwrap();
+
+    let loaded = UiPrefs::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut restored = App::new(rx);
+    restored.apply_prefs(&loaded);
+    assert_eq!(restored.notes, app.notes);
+}
@@ -0,0 +1,718 @@
+use night_tool::connector::{ConnectFuture, Connector};
+use night_tool::scanner::{scan, scan_ports, scan_range, PortSelection, Protocol, ProgressEvent, ScanConfig, ScanEvent, ScanResult, UnsentCounts, BANNER_TRUNCATED};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+
+async fn collect(host: &str, start: u16, end: u16) -> Vec<ScanResult> {
+    collect_with(host, start, end, &ScanConfig::default()).await
+}
+
+async fn collect_with(host: &str, start: u16, end: u16, config: &ScanConfig) -> Vec<ScanResult> {
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(64);
+    scan_range(host, start, end, config, tx).await;
+
+    let mut results = Vec::new();
+    while let Some(event) = rx.recv().await {
+        let r = match event {
+            ScanEvent::Result(r) => r,
+            ScanEvent::Finished { .. } => break,
+            _ => continue,
+        };
+        results.push(r);
+    }
+    results
+}
+
+/// Every message of a scan that has returned (and so dropped its sender): the results,
+/// and everything else in the order it was sent.
+async fn drain(mut rx: mpsc::Receiver<ScanEvent>) -> (Vec<ScanResult>, Vec<ScanEvent>) {
+    let (mut results, mut events) = (Vec::new(), Vec::new());
+    while let Some(event) = rx.recv().await {
+        match event {
+            ScanEvent::Result(r) => results.push(r),
+            other => events.push(other),
+        }
+    }
+    (results, events)
+}
+
+async fn unbound_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[tokio::test]
+async fn listening_port_is_reported_open() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((_stream, _)) = listener.accept().await {}
+    });
+
+    let results = collect("127.0.0.1", port, port).await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].port, port);
+    assert_eq!(results[0].status, "open");
+    // Connect is instant on loopback; the silent service costs at most the 500ms banner read.
+    assert!(results[0].response_ms < 1500, "took {}ms", results[0].response_ms);
+}
+
+#[tokio::test]
+async fn banner_is_captured_from_greeting_service() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream.write_all(b"220 test ready\r\n").await;
+        }
+    });
+
+    let results = collect("127.0.0.1", port, port).await;
+
+    assert_eq!(results[0].status, "open");
+    assert_eq!(results[0].banner.as_deref(), Some("220 test ready"));
+    assert!(!results[0].read_timed_out);
+}
+
+#[tokio::test]
+async fn silent_service_is_open_with_read_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let results = collect("127.0.0.1", port, port).await;
+
+    assert_eq!(results[0].status, "open");
+    assert!(results[0].read_timed_out);
+    assert!(results[0].banner.is_none());
+}
+
+#[tokio::test]
+async fn long_banner_is_truncated_at_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nServer: chatty\r\n\r\n").await;
+        }
+    });
+
+    let config = ScanConfig { max_banner_bytes: Some(15), ..Default::default() };
+    let results = collect_with("127.0.0.1", port, port, &config).await;
+
+    assert_eq!(results[0].banner.as_deref(), Some(format!("HTTP/1.1 200 OK{}", BANNER_TRUNCATED).as_str()));
+}
+
+#[tokio::test]
+async fn unbound_port_is_reported_closed() {
+    let port = unbound_port().await;
+
+    let results = collect("127.0.0.1", port, port).await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status, "closed");
+    assert!(results[0].error.is_some());
+    assert!(results[0].response_ms < 1000, "took {}ms", results[0].response_ms);
+}
+
+#[tokio::test]
+async fn cidr_target_reports_each_address() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let mut results = collect("127.0.0.0/30", port, port).await;
+    results.sort_by(|a, b| a.host.cmp(&b.host));
+
+    let hosts: Vec<&str> = results.iter().map(|r| r.host.as_str()).collect();
+    assert_eq!(hosts, ["127.0.0.0", "127.0.0.1", "127.0.0.2", "127.0.0.3"]);
+}
+
+#[tokio::test]
+async fn progress_ends_with_finished_counts() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (progress_tx, mut progress_rx) = mpsc::channel::<ProgressEvent>(16);
+    let config = ScanConfig { progress: Some(progress_tx), ..Default::default() };
+
+    collect_with("127.0.0.1", port, port, &config).await;
+    drop(config);
+
+    let mut last = None;
+    while let Some(event) = progress_rx.recv().await {
+        last = Some(event);
+    }
+    assert_eq!(last, Some(ProgressEvent::Finished { completed: 1, total: 1, open: 1 }));
+}
+
+#[tokio::test]
+async fn port_list_probes_each_port_once() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap().port();
+    let closed = unbound_port().await;
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(64);
+    scan_ports("127.0.0.1", vec![open, closed, open], &ScanConfig::default(), tx).await;
+
+    let mut ports = Vec::new();
+    while let Some(event) = rx.recv().await {
+        let r = match event {
+            ScanEvent::Result(r) => r,
+            ScanEvent::Finished { .. } => break,
+            _ => continue,
+        };
+        ports.push(r.port);
+    }
+    ports.sort_unstable();
+    let mut expected = vec![open, closed];
+    expected.sort_unstable();
+    assert_eq!(ports, expected);
+}
+
+#[tokio::test]
+async fn open_only_sends_open_ports_and_tallies_the_rest() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap().port();
+    let closed = [unbound_port().await, unbound_port().await];
+    let probed = Arc::new(AtomicUsize::new(0));
+    let unsent = Arc::new(UnsentCounts::default());
+    let config = ScanConfig {
+        open_only: true,
+        probed: Some(probed.clone()),
+        unsent: Some(unsent.clone()),
+        ..Default::default()
+    };
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_ports("127.0.0.1", vec![open, closed[0], closed[1]], &config, tx).await;
+
+    let (results, events) = drain(rx).await;
+    let sent: Vec<(u16, String)> = results.into_iter().map(|r| (r.port, r.status)).collect();
+    assert_eq!(sent, vec![(open, "open".to_string())]);
+    // The last progress event still counts the probes that weren't sent.
+    assert!(matches!(events[events.len() - 2..], [ScanEvent::Progress { done: 3, total: 3 }, ScanEvent::Finished { .. }]));
+    assert_eq!(probed.load(Ordering::Relaxed), 3);
+    assert_eq!(unsent.snapshot(), (2, 0, 0));
+}
+
+#[tokio::test]
+async fn finished_is_the_last_message() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((_stream, _)) = listener.accept().await {}
+    });
+    let closed = unbound_port().await;
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_ports("127.0.0.1", vec![open, closed], &ScanConfig::default(), tx).await;
+
+    let (results, events) = drain(rx).await;
+    assert!(matches!(events.last(), Some(ScanEvent::Finished { cancelled: false, .. })));
+    assert!(matches!(events[events.len() - 2], ScanEvent::Progress { done: 2, total: 2 }));
+    let mut messages: Vec<(u16, String)> = results.into_iter().map(|r| (r.port, r.status)).collect();
+    messages.sort();
+    let mut expected = vec![(open, "open".to_string()), (closed, "closed".to_string())];
+    expected.sort();
+    assert_eq!(messages, expected);
+}
+
+#[tokio::test]
+async fn answering_udp_service_is_reported_open() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        let (_, peer) = socket.recv_from(&mut buf).await.unwrap();
+        socket.send_to(b"pong", peer).await.unwrap();
+    });
+
+    let config = ScanConfig { protocols: vec![Protocol::Udp], ..Default::default() };
+    let results = collect_with("127.0.0.1", port, port, &config).await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].protocol, Protocol::Udp);
+    assert_eq!(results[0].status, "open");
+    assert_eq!(results[0].banner.as_deref(), Some("pong"));
+}
+
+/// Fails every connect with a fixed error kind, or never completes when `None`.
+#[derive(Debug)]
+struct FailingConnector(Option<std::io::ErrorKind>);
+
+impl Connector for FailingConnector {
+    fn connect<'a>(&'a self, _host: &'a str, _port: u16) -> ConnectFuture<'a> {
+        let kind = self.0;
+        Box::pin(async move {
+            match kind {
+                Some(kind) => Err(std::io::Error::from(kind)),
+                None => std::future::pending().await,
+            }
+        })
+    }
+}
+
+async fn status_with(connector: FailingConnector) -> String {
+    let config = ScanConfig {
+        connector: Some(Arc::new(connector)),
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    collect_with("127.0.0.1", 9, 9, &config).await.remove(0).status
+}
+
+#[tokio::test]
+async fn connect_failures_are_classified() {
+    use std::io::ErrorKind;
+    assert_eq!(status_with(FailingConnector(Some(ErrorKind::ConnectionRefused))).await, "closed");
+    assert_eq!(status_with(FailingConnector(Some(ErrorKind::ConnectionReset))).await, "reset");
+    assert_eq!(status_with(FailingConnector(Some(ErrorKind::HostUnreachable))).await, "unreachable");
+    assert_eq!(status_with(FailingConnector(None)).await, "filtered");
+}
+
+#[tokio::test]
+async fn cancelled_scan_ends_with_a_cancelled_finish() {
+    let cancel = Arc::new(AtomicBool::new(true));
+    let config = ScanConfig { cancel: Some(cancel), ..Default::default() };
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_range("127.0.0.1", 1, 1000, &config, tx).await;
+
+    let (results, events) = drain(rx).await;
+    assert!(results.is_empty());
+    assert!(matches!(events.last(), Some(ScanEvent::Finished { cancelled: true, .. })));
+}
+
+#[tokio::test]
+async fn seeded_shuffle_is_reproducible() {
+    let config = ScanConfig { shuffle: true, seed: Some(7), concurrency: Some(1), ..Default::default() };
+    let order = |results: Vec<ScanResult>| results.into_iter().map(|r| r.port).collect::<Vec<u16>>();
+    let first = order(collect_with("127.0.0.1", 1, 20, &config).await);
+    let second = order(collect_with("127.0.0.1", 1, 20, &config).await);
+
+    assert_eq!(first, second);
+    assert_ne!(first, (1..=20).collect::<Vec<u16>>());
+    let mut sorted = first.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (1..=20).collect::<Vec<u16>>());
+}
+
+#[tokio::test]
+async fn max_pps_spaces_out_connection_attempts() {
+    let config = ScanConfig { max_pps: Some(20), ..Default::default() };
+    let t0 = std::time::Instant::now();
+    let results = collect_with("127.0.0.1", 1, 10, &config).await;
+
+    assert_eq!(results.len(), 10);
+    // The first attempt starts at once, the other nine 50ms apart.
+    assert!(t0.elapsed() >= Duration::from_millis(400), "finished in {:?}", t0.elapsed());
+}
+
+#[tokio::test]
+async fn unresolvable_host_fails_once_without_probing() {
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_range("no-such-host.invalid", 1, 1000, &ScanConfig::default(), tx).await;
+
+    let (results, events) = drain(rx).await;
+    assert!(results.is_empty());
+    assert!(matches!(&events[0], ScanEvent::Error(e) if e.contains("no-such-host.invalid")));
+    assert_eq!(events.iter().filter(|e| matches!(e, ScanEvent::Error(_))).count(), 1);
+    assert!(matches!(events.last(), Some(ScanEvent::Finished { cancelled: false, .. })));
+}
+
+#[tokio::test]
+async fn hostname_targets_keep_their_name_in_results() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let results = collect("localhost", port, port).await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].host, "localhost");
+}
+
+#[tokio::test]
+async fn ipv6_literal_targets_connect() {
+    // Skip where the sandbox has no IPv6 loopback.
+    let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+        return;
+    };
+    let port = listener.local_addr().unwrap().port();
+
+    for host in ["::1", "[::1]"] {
+        let results = collect(host, port, port).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].host.as_str(), results[0].status.as_str()), ("::1", "open"));
+    }
+}
+
+#[tokio::test]
+async fn paused_scan_waits_until_resumed() {
+    let pause = Arc::new(AtomicBool::new(true));
+    let config = ScanConfig { pause: Some(pause.clone()), ..Default::default() };
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(64);
+    let scan = tokio::spawn(async move { scan_range("127.0.0.1", 1, 5, &config, tx).await });
+
+    // Progress keeps coming while paused, but no results.
+    let first_result = async {
+        while let Some(event) = rx.recv().await {
+            if matches!(event, ScanEvent::Result(_)) {
+                return;
+            }
+        }
+    };
+    assert!(tokio::time::timeout(Duration::from_millis(300), first_result).await.is_err());
+    pause.store(false, std::sync::atomic::Ordering::Relaxed);
+    let mut ports = Vec::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            ScanEvent::Result(r) => ports.push(r.port),
+            ScanEvent::Finished { cancelled, .. } => {
+                assert!(!cancelled);
+                break;
+            }
+            _ => {}
+        }
+    }
+    scan.await.unwrap();
+    assert_eq!(ports.len(), 5);
+}
+
+/// Ignores the first connection attempt, then connects for real.
+#[derive(Debug, Default)]
+struct FlakyConnector(std::sync::atomic::AtomicUsize);
+
+impl Connector for FlakyConnector {
+    fn connect<'a>(&'a self, host: &'a str, port: u16) -> ConnectFuture<'a> {
+        let first = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0;
+        Box::pin(async move {
+            if first {
+                std::future::pending::<()>().await;
+            }
+            let stream = tokio::net::TcpStream::connect((host, port)).await?;
+            Ok(Box::new(stream) as night_tool::connector::BoxedStream)
+        })
+    }
+}
+
+#[tokio::test]
+async fn retried_port_reports_the_answering_attempt() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((_stream, _)) = listener.accept().await {}
+    });
+    let config = ScanConfig {
+        connector: Some(Arc::new(FlakyConnector::default())),
+        timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+
+    let r = collect_with("127.0.0.1", port, port, &config).await.remove(0);
+    assert_eq!(r.status, "open");
+    assert!(r.response_ms < 200, "took {}ms", r.response_ms);
+    assert_eq!(r.attempts, 2);
+}
+
+/// Never connects, counting every attempt.
+#[derive(Debug, Default)]
+struct SilentConnector(Arc<std::sync::atomic::AtomicUsize>);
+
+impl Connector for SilentConnector {
+    fn connect<'a>(&'a self, _host: &'a str, _port: u16) -> ConnectFuture<'a> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Box::pin(std::future::pending())
+    }
+}
+
+#[tokio::test]
+async fn silent_port_is_retried_as_configured() {
+    let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let config = ScanConfig {
+        connector: Some(Arc::new(SilentConnector(attempts.clone()))),
+        timeout: Some(Duration::from_millis(20)),
+        retries: Some(3),
+        retry_backoff: Some(Duration::from_millis(1)),
+        ..Default::default()
+    };
+
+    let r = collect_with("127.0.0.1", 9, 9, &config).await.remove(0);
+    assert_eq!(r.status, "filtered");
+    assert_eq!(r.attempts, 4);
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+    // Asking for more than MAX_RETRIES is capped.
+    attempts.store(0, std::sync::atomic::Ordering::SeqCst);
+    let config = ScanConfig { retries: Some(50), ..config };
+    let r = collect_with("127.0.0.1", 9, 9, &config).await.remove(0);
+    assert_eq!(usize::from(r.attempts), usize::from(night_tool::scanner::MAX_RETRIES) + 1);
+}
+
+#[tokio::test]
+async fn deadline_aborts_probes_in_flight() {
+    let config = ScanConfig {
+        connector: Some(Arc::new(SilentConnector(Arc::default()))),
+        timeout: Some(Duration::from_secs(30)),
+        deadline: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    let t0 = std::time::Instant::now();
+    scan_range("127.0.0.1", 1, 100, &config, tx).await;
+    assert!(t0.elapsed() < Duration::from_secs(5), "took {:?}", t0.elapsed());
+
+    let (results, events) = drain(rx).await;
+    assert!(results.is_empty());
+    match events.last() {
+        Some(ScanEvent::Finished { elapsed, cancelled: false, timed_out: true }) => assert!(*elapsed >= Duration::from_millis(300)),
+        other => panic!("expected a timed-out finish, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn scan_stops_after_enough_open_ports() {
+    let mut ports = Vec::new();
+    for _ in 0..4 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        ports.push(listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            while let Ok((_stream, _)) = listener.accept().await {}
+        });
+    }
+    ports.push(unbound_port().await);
+    let config = ScanConfig { stop_after_open: Some(2), concurrency: Some(1), ..Default::default() };
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_ports("127.0.0.1", ports, &config, tx).await;
+
+    let (results, events) = drain(rx).await;
+    assert_eq!(results.iter().filter(|r| r.status == "open").count(), 2);
+    assert!(matches!(events.last(), Some(ScanEvent::Finished { cancelled: false, .. })));
+}
+
+#[tokio::test]
+async fn scan_reads_target_and_ports_from_the_config() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap().port();
+    let closed = unbound_port().await;
+    let ports = PortSelection::List(vec![closed, open, closed]);
+    assert_eq!(ports.port_count(), 2);
+    let config = ScanConfig { host: "127.0.0.1".to_string(), ports, ..Default::default() };
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan(config, tx).await;
+
+    let (results, events) = drain(rx).await;
+    assert!(matches!(events.last(), Some(ScanEvent::Finished { .. })));
+    let mut seen: Vec<(u16, String)> = results.into_iter().map(|r| (r.port, r.status)).collect();
+    seen.sort();
+    let mut expected = vec![(open, "open".to_string()), (closed, "closed".to_string())];
+    expected.sort();
+    assert_eq!(seen, expected);
+    assert_eq!(PortSelection::Range(20, 25).port_count(), 6);
+}
+
+#[tokio::test]
+async fn zero_banner_bytes_skips_the_banner_read() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            use tokio::io::AsyncWriteExt;
+            let _ = stream.write_all(b"220 ready\r\n").await;
+        }
+    });
+    let config = ScanConfig { max_banner_bytes: Some(0), ..Default::default() };
+
+    let r = collect_with("127.0.0.1", port, port, &config).await.remove(0);
+    assert_eq!((r.status.as_str(), r.banner, r.read_timed_out), ("open", None, false));
+}
+
+/// A SOCKS5 proxy that relays CONNECTs for `relay_port` to localhost and refuses the rest.
+async fn fake_socks5_proxy(relay_port: u16) -> std::net::SocketAddr {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut greeting = [0u8; 3];
+                client.read_exact(&mut greeting).await.ok()?;
+                client.write_all(&[5, 0]).await.ok()?;
+                let mut request = [0u8; 5];
+                client.read_exact(&mut request).await.ok()?;
+                // Only domain targets are expected: the length byte, the name, the port.
+                assert_eq!(request[3], 3, "target sent as a domain name");
+                let mut rest = vec![0u8; usize::from(request[4]) + 2];
+                client.read_exact(&mut rest).await.ok()?;
+                let port = u16::from_be_bytes([rest[rest.len() - 2], rest[rest.len() - 1]]);
+                if port != relay_port {
+                    client.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).await.ok()?;
+                    return None;
+                }
+                let mut upstream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.ok()?;
+                client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).await.ok()?;
+                tokio::io::copy_bidirectional(&mut client, &mut upstream).await.ok()
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn proxied_scan_reads_banners_and_refusals_through_the_proxy() {
+    use tokio::io::AsyncWriteExt;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream.write_all(b"SSH-2.0-behind-proxy\r\n").await;
+        }
+    });
+    let refused = unbound_port().await;
+    let config = ScanConfig { proxy: Some(fake_socks5_proxy(port).await), ..Default::default() };
+
+    // Only the proxy can resolve this name; a local lookup would fail the scan.
+    let (tx, rx) = mpsc::channel::<ScanEvent>(64);
+    scan_ports("jumpbox.invalid", vec![port, refused], &config, tx).await;
+    let (mut results, _) = drain(rx).await;
+    results.sort_by_key(|r| r.port != port);
+
+    assert_eq!(results.len(), 2, "{:?}", results);
+    assert_eq!(results[0].status, "open");
+    assert_eq!(results[0].banner.as_deref(), Some("SSH-2.0-behind-proxy"));
+    assert_eq!(results[1].status, "closed");
+}
+
+/// Connects every port to one local listener, so tests can serve well-known ports.
+#[derive(Debug)]
+struct RelayConnector(std::net::SocketAddr);
+
+impl Connector for RelayConnector {
+    fn connect<'a>(&'a self, _host: &'a str, _port: u16) -> ConnectFuture<'a> {
+        let addr = self.0;
+        Box::pin(async move {
+            let stream = tokio::net::TcpStream::connect(addr).await?;
+            Ok(Box::new(stream) as night_tool::connector::BoxedStream)
+        })
+    }
+}
+
+#[tokio::test]
+async fn multi_line_smtp_greeting_is_read_to_its_final_line() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let _ = stream.write_all(b"220-mail.example.com ESMTP Exim 4.96\r\n").await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let _ = stream.write_all(b"220 ready\r\n").await;
+                // Anything after the final line is not part of the greeting.
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+                let _ = stream.write_all(b"250 late\r\n").await;
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            });
+        }
+    });
+
+    let config = ScanConfig {
+        connector: Some(Arc::new(RelayConnector(addr))),
+        greeting_aware: true,
+        banner_timeout: Some(Duration::from_secs(3)),
+        ..Default::default()
+    };
+    let r = collect_with("127.0.0.1", 25, 25, &config).await.remove(0);
+    assert_eq!(r.banner.as_deref(), Some("220-mail.example.com ESMTP Exim 4.96\r\n220 ready"));
+    assert!(r.response_ms < 1000, "read stops at the final line, took {}ms", r.response_ms);
+
+    // Without greeting awareness the first chunk is all that is kept.
+    let config = ScanConfig { greeting_aware: false, ..config };
+    let r = collect_with("127.0.0.1", 25, 25, &config).await.remove(0);
+    assert_eq!(r.banner.as_deref(), Some("220-mail.example.com ESMTP Exim 4.96"));
+}
+
+#[tokio::test]
+async fn banner_hard_cap_cuts_off_a_trickling_service() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                // A greeting line that never ends, one byte at a time.
+                let _ = stream.write_all(b"220-").await;
+                while stream.write_all(b"x").await.is_ok() {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            });
+        }
+    });
+
+    let config = ScanConfig {
+        connector: Some(Arc::new(RelayConnector(addr))),
+        greeting_aware: true,
+        banner_timeout: Some(Duration::from_secs(10)),
+        banner_hard_cap: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let r = collect_with("127.0.0.1", 25, 25, &config).await.remove(0);
+    assert_eq!(r.status, "open");
+    assert!(r.response_ms < 1500, "cut off at the cap, took {}ms", r.response_ms);
+    let banner = r.banner.expect("bytes read before the cap are kept");
+    assert!(banner.starts_with("220-x"), "{}", banner);
+}
+
+/// Holds every connect for a while, then refuses it, recording how many were in
+/// flight at once during the first `window` of the scan and overall.
+#[derive(Debug)]
+struct CountingConnector {
+    started: std::time::Instant,
+    window: Duration,
+    in_flight: AtomicUsize,
+    max_in_window: AtomicUsize,
+    max_overall: AtomicUsize,
+}
+
+impl Connector for CountingConnector {
+    fn connect<'a>(&'a self, _host: &'a str, _port: u16) -> ConnectFuture<'a> {
+        Box::pin(async move {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.started.elapsed() < self.window {
+                self.max_in_window.fetch_max(now, Ordering::SeqCst);
+            }
+            self.max_overall.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+        })
+    }
+}
+
+#[tokio::test]
+async fn ramp_limits_probes_in_flight_during_the_first_step() {
+    let connector = Arc::new(CountingConnector {
+        started: std::time::Instant::now(),
+        // A little short of the first ramp step, so scheduling jitter cannot blur it.
+        window: Duration::from_millis(250),
+        in_flight: AtomicUsize::new(0),
+        max_in_window: AtomicUsize::new(0),
+        max_overall: AtomicUsize::new(0),
+    });
+    let config = ScanConfig {
+        connector: Some(connector.clone()),
+        concurrency: Some(16),
+        ramp: Some(night_tool::scanner::Ramp { step: 2, interval: Duration::from_millis(300) }),
+        ..Default::default()
+    };
+    let results = collect_with("127.0.0.1", 1, 12, &config).await;
+
+    assert_eq!(results.len(), 12);
+    assert_eq!(connector.max_in_window.load(Ordering::SeqCst), 2, "only the first step is allowed at first");
+    assert!(connector.max_overall.load(Ordering::SeqCst) > 2, "later steps add permits");
+}
@@ -0,0 +1,62 @@
+use night_tool::scanner::{probe_for_service, scan_ports, Protocol, ScanConfig, ScanEvent};
+use night_tool::services::{set_services, ServiceMap};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// A mail server that stays silent until it is sent an empty line.
+async fn shy_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 64];
+                if let Ok(n) = stream.read(&mut buf).await && &buf[..n] == b"\r\n" {
+                    let _ = stream.write_all(b"-ERR unknown command (Dovecot 2.3.21)\r\n").await;
+                }
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn probes_are_chosen_by_service_name() {
+    assert_eq!(probe_for_service("smtp"), Some(&b"\r\n"[..]));
+    assert_eq!(probe_for_service("elasticsearch"), Some(&b"GET / HTTP/1.0\r\n\r\n"[..]));
+    assert_eq!(probe_for_service("ssh"), None);
+    assert_eq!(probe_for_service("http"), None);
+}
+
+#[tokio::test]
+async fn line_based_services_are_nudged_unless_denied() {
+    let (probed, denied) = (shy_server().await, shy_server().await);
+    let mut names = ServiceMap::new();
+    names.insert((probed, Protocol::Tcp), "pop3".to_string());
+    names.insert((denied, Protocol::Tcp), "pop3".to_string());
+    assert!(set_services(names));
+
+    let config = ScanConfig {
+        probe_deny: vec![denied],
+        banner_timeout: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let (tx, mut rx) = mpsc::channel::<ScanEvent>(8);
+    scan_ports("127.0.0.1", vec![probed, denied], &config, tx).await;
+
+    let mut results = Vec::new();
+    while let Some(event) = rx.recv().await {
+        let r = match event {
+            ScanEvent::Result(r) => r,
+            ScanEvent::Finished { .. } => break,
+            _ => continue,
+        };
+        results.push(r);
+    }
+    let by_port = |port| results.iter().find(|r| r.port == port).unwrap();
+
+    assert_eq!(by_port(probed).banner.as_deref(), Some("-ERR unknown command (Dovecot 2.3.21)"));
+    assert_eq!(by_port(denied).banner, None);
+}
@@ -0,0 +1,54 @@
+use night_tool::scanner::Protocol;
+use night_tool::services::{identify_service, identify_service_for, load_services, parse_version, set_services};
+
+#[test]
+fn services_file_is_consulted_per_protocol() {
+    let path = std::env::temp_dir().join(format!("night_tool_services_{}", std::process::id()));
+    std::fs::write(
+        &path,
+        "# comment line\n\
+         ajp13\t8009/tcp\t0.001\t# Apache JServ\n\
+         snmp\t161/udp\t0.433\n\
+         bogus\tnot-a-port\n\
+         sctp-only\t9/sctp\n",
+    )
+    .unwrap();
+    let map = load_services(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(map.len(), 2);
+    assert!(set_services(map));
+
+    assert_eq!(identify_service(8009), "ajp13");
+    assert_eq!(identify_service_for(161, Protocol::Udp), "snmp");
+    assert_eq!(identify_service_for(8009, Protocol::Udp), "unknown");
+    assert_eq!(identify_service(80), "http");
+    assert_eq!(identify_service(40000), "unknown");
+}
+
+#[test]
+fn versions_are_read_from_common_banners() {
+    let cases = [
+        ("ssh", "SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6", Some(("OpenSSH", "8.9p1"))),
+        ("ssh", "SSH-2.0-dropbear_2022.83", Some(("dropbear", "2022.83"))),
+        ("ftp", "220 ProFTPD 1.3.5 Server (Debian) [::ffff:10.0.0.5]", Some(("ProFTPD", "1.3.5"))),
+        ("ftp", "220 (vsFTPd 3.0.3)", Some(("vsFTPd", "3.0.3"))),
+        ("smtp", "220 mail.example.com ESMTP Exim 4.96 Mon, 14 Oct 2024 10:00:00 +0000", Some(("Exim", "4.96"))),
+        ("http", "HTTP/1.1 200 OK | Server: nginx/1.24.0 | Title: Welcome to nginx!", Some(("nginx", "1.24.0"))),
+        ("http-alt", "HTTP/1.1 404 Not Found | Server: Apache/2.4.57 (Debian)", Some(("Apache", "2.4.57"))),
+        ("http", "HTTP/1.1 301 Moved Permanently | Server: cloudflare", None),
+        ("smtp", "220 mail.example.com ESMTP Postfix (Ubuntu)", None),
+        ("ssh", "SSH-2.0-Go", None),
+        ("https", "CN=example.com exp=2025-06-01", None),
+    ];
+    for (service, banner, want) in cases {
+        let got = parse_version(service, banner);
+        assert_eq!(got.as_ref().map(|s| (s.product.as_str(), s.version.as_str())), want, "{}", banner);
+    }
+}
+
+#[test]
+fn software_displays_as_product_then_version() {
+    let software = parse_version("ssh", "SSH-2.0-OpenSSH_8.9p1 Ubuntu-3").unwrap();
+    assert_eq!(software.to_string(), "OpenSSH 8.9p1");
+}
@@ -0,0 +1,41 @@
+use night_tool::scanner::{parse_targets, MAX_TARGETS};
+use std::net::IpAddr;
+
+fn ips(list: &[&str]) -> Vec<IpAddr> {
+    list.iter().map(|s| s.parse().unwrap()).collect()
+}
+
+#[test]
+fn single_address_is_its_own_target() {
+    assert_eq!(parse_targets("10.0.0.7"), ips(&["10.0.0.7"]));
+    assert_eq!(parse_targets(" ::1 "), ips(&["::1"]));
+}
+
+#[test]
+fn cidr_block_expands_from_the_network_address() {
+    assert_eq!(parse_targets("192.168.1.5/30"), ips(&["192.168.1.4", "192.168.1.5", "192.168.1.6", "192.168.1.7"]));
+    assert_eq!(parse_targets("10.0.0.0/24").len(), 256);
+    assert_eq!(parse_targets("fd00::/126"), ips(&["fd00::", "fd00::1", "fd00::2", "fd00::3"]));
+}
+
+#[test]
+fn sixteen_bit_blocks_are_the_largest_expanded() {
+    assert_eq!(parse_targets("10.1.0.0/16").len() as u128, MAX_TARGETS);
+    assert!(parse_targets("10.0.0.0/15").is_empty());
+    assert!(parse_targets("fd00::/100").is_empty());
+}
+
+#[test]
+fn hostnames_and_malformed_blocks_yield_nothing() {
+    assert!(parse_targets("example.com").is_empty());
+    assert!(parse_targets("10.0.0.0/33").is_empty());
+    assert!(parse_targets("10.0.0.0/x").is_empty());
+}
+
+#[test]
+fn bracketed_ipv6_literals_are_accepted() {
+    assert_eq!(parse_targets("[::1]"), ips(&["::1"]));
+    assert_eq!(parse_targets(" [fd00::1] "), ips(&["fd00::1"]));
+    assert_eq!(parse_targets("[fd00::]/127"), ips(&["fd00::", "fd00::1"]));
+    assert!(parse_targets("[example.com]").is_empty());
+}
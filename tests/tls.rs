@@ -0,0 +1,82 @@
+use night_tool::tls::{inspect_certificate, parse_certificate, CertSummary};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const UTC_CERT: &[u8] = include_bytes!("data/cert_utctime.der");
+const GENERALIZED_CERT: &[u8] = include_bytes!("data/cert_generalized.der");
+
+fn summary(cn: &str, exp: &str) -> CertSummary {
+    CertSummary { common_name: Some(cn.to_string()), not_after: Some(exp.to_string()) }
+}
+
+fn record(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind, 0x03, 0x03];
+    out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+fn handshake(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(body);
+    out
+}
+
+#[test]
+fn certificates_are_summarized() {
+    assert_eq!(parse_certificate(UTC_CERT), Some(summary("scanme.example", "2027-10-14")));
+    assert_eq!(parse_certificate(GENERALIZED_CERT), Some(summary("far-future.example", "2056-11-25")));
+    assert_eq!(summary("example.com", "2025-06-01").to_string(), "CN=example.com exp=2025-06-01");
+    assert_eq!(parse_certificate(&UTC_CERT[..100]), None);
+    assert_eq!(parse_certificate(b"not a certificate"), None);
+}
+
+#[tokio::test]
+async fn certificate_is_read_from_a_tls12_handshake() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut hello = vec![0u8; 1024];
+        let n = stream.read(&mut hello).await.unwrap();
+        hello.truncate(n);
+
+        let mut cert = (UTC_CERT.len() as u32).to_be_bytes()[1..].to_vec();
+        cert.extend_from_slice(UTC_CERT);
+        let mut list = (cert.len() as u32).to_be_bytes()[1..].to_vec();
+        list.extend_from_slice(&cert);
+        let mut flight = handshake(2, &[0x03, 0x03]);
+        flight.extend(handshake(11, &list));
+        // Split the flight so the Certificate message spans two records.
+        let (a, b) = flight.split_at(40);
+        stream.write_all(&record(22, a)).await.unwrap();
+        stream.write_all(&record(22, b)).await.unwrap();
+        hello
+    });
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let cert = inspect_certificate(&mut stream, Some("scanme.example"), Duration::from_secs(2)).await;
+    assert_eq!(cert, Some(summary("scanme.example", "2027-10-14")));
+
+    let hello = server.await.unwrap();
+    assert_eq!(&hello[..3], &[22, 0x03, 0x01]);
+    assert!(hello.windows(14).any(|w| w == b"scanme.example"));
+}
+
+#[tokio::test]
+async fn alerts_yield_no_summary() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await;
+        // protocol_version alert, as sent by TLS 1.3-only servers.
+        let _ = stream.write_all(&record(21, &[2, 70])).await;
+    });
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    assert_eq!(inspect_certificate(&mut stream, None, Duration::from_secs(2)).await, None);
+}
@@ -0,0 +1,30 @@
+use night_tool::ports::{load_top_ports, set_top_ports, top_ports};
+use night_tool::scanner::{PortSelection, TOP_PORTS};
+
+#[test]
+fn ranked_file_replaces_the_built_in_list() {
+    assert_eq!(top_ports(3), vec![80, 23, 443]);
+    assert_eq!(top_ports(usize::MAX).len(), TOP_PORTS.len());
+    assert_eq!(PortSelection::TopN(10).port_count(), 10);
+
+    let path = std::env::temp_dir().join(format!("night_tool_top_ports_{}", std::process::id()));
+    std::fs::write(&path, "# most important first\n8443 22, 80\n20-21 # ftp\n22\n").unwrap();
+    let ranked = load_top_ports(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(ranked, vec![8443, 22, 80, 20, 21]);
+    assert!(set_top_ports(ranked));
+    assert_eq!(top_ports(2), vec![8443, 22]);
+    assert_eq!(PortSelection::Top.port_count(), 5);
+    assert_eq!(PortSelection::TopN(1000).port_count(), 5);
+}
+
+#[test]
+fn ranked_file_with_a_bad_port_names_its_line() {
+    let path = std::env::temp_dir().join(format!("night_tool_top_ports_bad_{}", std::process::id()));
+    std::fs::write(&path, "22\n80 http\n").unwrap();
+    let err = load_top_ports(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(err.to_string(), "line 2: invalid port 'http'");
+}
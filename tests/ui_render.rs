@@ -0,0 +1,826 @@
+use night_tool::scanner::{Protocol, ScanEvent, ScanResult};
+use night_tool::services::Software;
+use night_tool::ui::{self, App};
+use ratatui::{backend::TestBackend, Terminal};
+use tokio::sync::mpsc;
+
+fn screen_text(terminal: &Terminal<TestBackend>) -> String {
+    let buffer = terminal.backend().buffer();
+    let width = buffer.area.width as usize;
+    buffer
+        .content()
+        .chunks(width)
+        .map(|row| row.iter().map(|c| c.symbol()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn draw_renders_results_and_detail() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.host_input = "127.0.0.1".to_string();
+    app.results.push(ScanResult {
+        host: "127.0.0.1".to_string(),
+        port: 22,
+        protocol: Protocol::Tcp,
+        status: "open".to_string(),
+        service: "ssh".to_string(),
+        response_ms: 3,
+        banner: Some("SSH-2.0-OpenSSH_9.6".to_string()),
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: Some(Software { product: "OpenSSH".to_string(), version: "9.6".to_string() }),
+    });
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+
+    assert!(text.contains("Target: 127.0.0.1"));
+    assert!(text.contains("Results"));
+    assert!(text.contains("Mode: EDITING host"));
+    assert!(text.contains("Port: 22/tcp"));
+    assert!(text.contains("State: open"));
+    assert!(text.contains("Service: ssh (OpenSSH 9.6)"));
+    assert!(text.contains("Open ports (1)"));
+    assert!(text.contains("22/tcp ssh"));
+}
+
+#[test]
+fn tuning_fields_fall_back_on_invalid_values() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.tuning(), (None, None, None));
+
+    app.concurrency_input = "64".to_string();
+    app.timeout_ms_input = "5000".to_string();
+    app.max_pps_input = "200".to_string();
+    assert_eq!(app.tuning(), (Some(64), Some(std::time::Duration::from_millis(5000)), Some(200)));
+
+    app.concurrency_input = "0".to_string();
+    app.timeout_ms_input = "50".to_string();
+    app.max_pps_input = "0".to_string();
+    assert_eq!(app.tuning(), (None, None, None));
+    assert_eq!(app.log_events.len(), 3);
+}
+
+#[test]
+fn retries_field_is_capped() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.retries(), None);
+
+    app.retries_input = "3".to_string();
+    assert_eq!(app.retries(), Some(3));
+
+    app.retries_input = "40".to_string();
+    assert_eq!(app.retries(), Some(night_tool::scanner::MAX_RETRIES));
+    app.retries_input = "lots".to_string();
+    assert_eq!(app.retries(), None);
+    assert_eq!(app.log_events.len(), 2);
+}
+
+#[test]
+fn proxy_field_must_be_an_address_and_port() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.proxy(), Ok(None));
+
+    app.proxy_input = " 127.0.0.1:1080 ".to_string();
+    assert_eq!(app.proxy(), Ok(Some("127.0.0.1:1080".parse().unwrap())));
+
+    app.proxy_input = "127.0.0.1".to_string();
+    assert!(app.proxy().is_err());
+}
+
+#[test]
+fn progress_gauge_tracks_scanned_share() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.is_scanning = true;
+    app.total_ports = 4;
+    app.total_scanned = 1;
+    assert_eq!(app.progress(), 0.25);
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("25%"));
+    assert!(screen_text(&terminal).contains("ETA: --"));
+
+    app.is_scanning = false;
+    app.scan_finished_wall = Some(std::time::SystemTime::now());
+    assert_eq!(app.progress(), 1.0);
+}
+
+#[test]
+fn progress_events_count_results_that_were_not_sent() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    let result = |port| ScanResult {
+        host: "127.0.0.1".to_string(),
+        port,
+        protocol: Protocol::Tcp,
+        status: "open".to_string(),
+        service: "unknown".to_string(),
+        response_ms: 1,
+        banner: None,
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: None,
+    };
+    app.begin_scan("127.0.0.1");
+    app.apply_progress(5);
+    // Results the progress count already covered are not counted twice.
+    for port in 1..=3 {
+        app.apply_result(result(port));
+    }
+    assert_eq!(app.total_scanned, 5);
+    for port in 4..=6 {
+        app.apply_result(result(port));
+    }
+    assert_eq!(app.total_scanned, 6);
+    app.apply_progress(4);
+    assert_eq!(app.total_scanned, 6);
+}
+
+#[test]
+fn selection_moves_with_arrows_and_clamps() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.move_selection(-1);
+    assert_eq!(app.selected, None);
+
+    for port in [22, 80, 443] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: "open".to_string(),
+            service: "unknown".to_string(),
+            response_ms: 1,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    app.move_selection(-1);
+    assert_eq!(app.detail_result().map(|r| r.port), Some(80));
+    app.move_selection(-5);
+    assert_eq!(app.selected, Some(0));
+    app.move_selection(10);
+    assert_eq!(app.selected, Some(2));
+}
+
+#[test]
+fn open_only_filter_maps_selection_to_shown_rows() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for (port, status) in [(22, "open"), (23, "closed"), (80, "open"), (81, "closed")] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: status.to_string(),
+            service: "unknown".to_string(),
+            response_ms: 1,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    app.toggle_show_only_open();
+    assert_eq!(app.visible_rows(), vec![0, 2]);
+    assert_eq!(app.detail_result().map(|r| r.port), Some(80));
+
+    app.move_selection(-1);
+    assert_eq!(app.detail_result().map(|r| r.port), Some(22));
+    assert!(!app.goto_port(23));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Results (open only)"));
+}
+
+#[test]
+fn scan_rate_freezes_when_the_scan_ends() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.scan_rate(), None);
+
+    app.begin_scan("127.0.0.1");
+    app.is_scanning = true;
+    app.total_scanned = 50;
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    app.sample_rate();
+    assert!(app.scan_rate().is_some_and(|r| r > 0.0));
+
+    app.is_scanning = false;
+    app.finish_job("completed");
+    let frozen = app.scan_rate().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert_eq!(app.scan_rate(), Some(frozen));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Rate: "));
+}
+
+#[test]
+fn eta_extrapolates_from_progress() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.begin_scan("127.0.0.1");
+    app.is_scanning = true;
+    app.total_ports = 4;
+    assert_eq!(app.eta(), None);
+
+    app.started_at = Some(std::time::Instant::now() - std::time::Duration::from_secs(10));
+    app.total_scanned = 1;
+    let eta = app.eta().unwrap().as_secs_f64();
+    assert!((30.0..31.0).contains(&eta), "eta {}", eta);
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("ETA: 31s") || screen_text(&terminal).contains("ETA: 30s"));
+
+    app.is_scanning = false;
+    assert_eq!(app.eta(), None);
+}
+
+#[test]
+fn help_popup_lists_key_bindings() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(!screen_text(&terminal).contains("Press any key to close"));
+
+    app.show_help = true;
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Press any key to close"));
+    for (key, _) in ui::KEY_HELP {
+        assert!(text.contains(key), "missing {}", key);
+    }
+}
+
+#[test]
+fn pausing_shows_in_status_and_stops_the_clock() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    assert_eq!(app.toggle_pause(), None);
+
+    app.begin_scan("127.0.0.1");
+    app.is_scanning = true;
+    app.pause = Some(std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)));
+    assert_eq!(app.toggle_pause(), Some(true));
+    assert!(app.pause.as_ref().unwrap().load(std::sync::atomic::Ordering::Relaxed));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Status: PAUSED"));
+
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(app.scan_elapsed().unwrap() < std::time::Duration::from_millis(50));
+    assert_eq!(app.toggle_pause(), Some(false));
+    assert!(app.scan_elapsed().unwrap() < std::time::Duration::from_millis(50));
+}
+
+#[test]
+fn repeated_log_lines_fold_into_a_count() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.clear_log();
+    for _ in 0..3 {
+        app.push_log("Scan already running");
+    }
+    assert_eq!(app.log_events, ["Scan already running (x3)"]);
+    assert_eq!(app.log_entries().count(), 1);
+}
+
+#[test]
+fn a_different_log_line_resets_the_repeat_count() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.clear_log();
+    for msg in ["Invalid port range", "Invalid port range", "Host is empty", "Invalid port range", "Invalid port range"] {
+        app.push_log(msg);
+    }
+    assert_eq!(app.log_events, ["Invalid port range (x2)", "Host is empty", "Invalid port range (x2)"]);
+}
+
+#[test]
+fn turning_coalescing_off_keeps_every_log_line() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.clear_log();
+    app.toggle_coalesce_log();
+    assert!(!app.coalesce_log);
+    assert_eq!(app.log_events, ["Log coalescing off"]);
+    app.push_log("No active scan");
+    app.push_log("No active scan");
+    assert_eq!(app.log_events, ["Log coalescing off", "No active scan", "No active scan"]);
+
+    app.toggle_coalesce_log();
+    app.push_log("No active scan");
+    app.push_log("No active scan");
+    assert_eq!(app.log_events[3..], ["Log coalescing on", "No active scan (x2)"]);
+}
+
+#[test]
+fn log_is_capped_and_can_be_cleared() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for i in 0..ui::MAX_LOG_EVENTS + 5 {
+        app.push_log(format!("event {}", i));
+    }
+    assert_eq!(app.log_events.len(), ui::MAX_LOG_EVENTS);
+    assert_eq!(app.log_events[0], "event 5");
+    assert_eq!(app.log_entries().count(), ui::MAX_LOG_EVENTS);
+
+    app.clear_log();
+    assert!(app.log_events.is_empty());
+    assert_eq!(app.log_entries().count(), 0);
+}
+
+#[test]
+fn log_scrolls_back_without_following_new_events() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for i in 0..30 {
+        app.push_log(format!("entry-{:03}", i));
+    }
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("entry-029"));
+    assert!(!text.contains("[scrolled back]"));
+
+    app.scroll_log(10);
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Log [scrolled back]"));
+    assert!(text.contains("entry-019"));
+    assert!(!text.contains("entry-020"));
+
+    app.push_log("entry-030".to_string());
+    assert_eq!(app.log_scroll, 11);
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("entry-019"));
+    assert!(!text.contains("entry-030"));
+
+    app.scroll_log(1000);
+    assert_eq!(app.log_scroll, 30);
+    app.scroll_log(-1000);
+    assert_eq!(app.log_scroll, 0);
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("entry-030"));
+}
+
+#[test]
+fn sorting_reorders_only_the_display() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for (port, ms, service) in [(443, 30, "https"), (22, 5, "ssh"), (80, 30, "http")] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: "open".to_string(),
+            service: service.to_string(),
+            response_ms: ms,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    assert_eq!(app.visible_rows(), vec![0, 1, 2]);
+
+    app.cycle_sort();
+    assert_eq!(app.sort_by, ui::SortBy::Port);
+    assert_eq!(app.visible_rows(), vec![1, 2, 0]);
+    app.cycle_sort();
+    app.sort_desc = true;
+    // Ties keep arrival order even when reversed.
+    assert_eq!(app.visible_rows(), vec![0, 2, 1]);
+    app.cycle_sort();
+    app.sort_desc = false;
+    assert_eq!(app.visible_rows(), vec![2, 0, 1]);
+    assert_eq!(app.results[0].port, 443);
+    // The Detail pane still follows the newest result, wherever it is shown.
+    assert_eq!(app.detail_result().map(|r| r.port), Some(80));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Results (by service ↑)"));
+}
+
+#[test]
+fn histogram_buckets_answered_ports() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for (port, status, ms) in [(22, "open", 3), (23, "closed", 9), (80, "open", 120), (81, "filtered", 1000), (443, "open", 450)] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: status.to_string(),
+            service: String::new(),
+            response_ms: ms,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    // Timeouts carry no latency signal and stay out of the chart.
+    assert_eq!(
+        night_tool::stats::response_histogram(&app.results),
+        vec![("0-10", 2), ("10-50", 0), ("50-200", 1), ("200+", 1)]
+    );
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(!screen_text(&terminal).contains("Response times"));
+    app.show_histogram = true;
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Response times"));
+    assert!(text.contains("50-200"));
+}
+
+#[test]
+fn focused_input_field_is_highlighted() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.host_input = "10.0.0.1".to_string();
+    app.start_port_input = "20".to_string();
+    app.end_port_input = "25".to_string();
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+
+    // Colour of the first cell of `title` on the top bar's field rows.
+    let title_color = |terminal: &Terminal<TestBackend>, title: &str| {
+        let buffer = terminal.backend().buffer();
+        let text = screen_text(terminal);
+        let (row, line) = text.lines().enumerate().take(6).find(|(_, l)| l.contains(title)).unwrap();
+        let col = line[..line.find(title).unwrap()].chars().count() as u16;
+        buffer[(col, row as u16)].fg
+    };
+    for (focus, focused, other) in [(1, "Start", "End"), (2, "End", "Start")] {
+        app.input_focus = focus;
+        terminal.draw(|f| ui::draw(f, &app)).unwrap();
+        let text = screen_text(&terminal);
+        assert!(text.contains("20") && text.contains("25"));
+        assert_eq!(title_color(&terminal, focused), ratatui::style::Color::Yellow);
+        assert_ne!(title_color(&terminal, other), ratatui::style::Color::Yellow);
+    }
+}
+
+#[test]
+fn port_fields_reject_letters_and_flag_bad_ranges() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.start_port_input.clear();
+    app.end_port_input.clear();
+    app.input_focus = 1;
+    for c in "8x0".chars() {
+        app.handle_char_input(c);
+    }
+    assert_eq!(app.start_port_input, "80");
+    app.input_focus = 2;
+    for c in "2a2-".chars() {
+        app.handle_char_input(c);
+    }
+    assert_eq!(app.end_port_input, "22-");
+    // A half-typed range doesn't parse as a port list yet.
+    assert_eq!(app.port_input_errors(), (false, true));
+
+    app.end_port_input = "22".to_string();
+    assert_eq!(app.port_input_errors(), (true, true));
+    assert!(!app.ports_valid());
+    app.end_port_input = "443".to_string();
+    assert!(app.ports_valid());
+    app.start_port_input = "70000".to_string();
+    assert_eq!(app.port_input_errors(), (true, false));
+    app.start_port_input.clear();
+    app.end_port_input.clear();
+    assert!(app.ports_valid(), "empty fields fall back to 1-65535");
+
+    app.input_focus = 0;
+    app.handle_char_input('x');
+    assert_eq!(app.host_input, "x");
+}
+
+#[test]
+fn clicking_a_results_row_selects_it() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for port in 1..=40 {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: "closed".to_string(),
+            service: String::new(),
+            response_ms: 1,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    let screen = ratatui::layout::Rect::new(0, 0, 120, 30);
+    let table = ui::results_area(screen, &app);
+    // Borders and the header row select nothing.
+    assert!(!app.click_row(table, table.y));
+    assert!(!app.click_row(table, table.y + 1));
+    assert!(app.click_row(table, table.y + 4));
+    assert_eq!(app.selected, Some(2));
+
+    // Once the table has scrolled to keep a low selection visible, clicks follow the scroll.
+    app.selected = Some(39);
+    let last_row = table.y + table.height - 2;
+    assert!(app.click_row(table, last_row));
+    assert_eq!(app.selected, Some(39));
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let top_row = screen_text(&terminal).lines().nth(usize::from(table.y + 2)).unwrap().to_string();
+    assert!(app.click_row(table, table.y + 2));
+    let picked = app.selected.unwrap();
+    assert_eq!(picked, 40 - usize::from(table.height - 3));
+    assert!(top_row.contains(&format!("│{:<8}", picked + 1)), "{}", top_row);
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains(&format!("Port: {}/tcp", picked + 1)));
+}
+
+#[test]
+fn resolve_failure_is_shown_in_place_of_results() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.scan_error = Some("cannot resolve nosuch.invalid: no address".to_string());
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Status: FAILED"));
+    assert!(text.contains("Scan failed: cannot resolve nosuch.invalid"));
+
+    app.begin_scan("127.0.0.1");
+    assert_eq!(app.scan_error, None);
+}
+
+#[test]
+fn failed_scan_is_not_recorded_as_completed() {
+    let (tx, rx) = mpsc::channel::<ScanEvent>(4);
+    let mut app = App::new(rx);
+    app.begin_scan("nosuch.invalid");
+    app.start_job("nosuch.invalid", "ports 1-100");
+    app.is_scanning = true;
+    tx.try_send(ScanEvent::Error("cannot resolve nosuch.invalid: no address".to_string())).unwrap();
+    tx.try_send(ScanEvent::Finished { elapsed: std::time::Duration::ZERO, cancelled: false, timed_out: false }).unwrap();
+    // The same handling the TUI gives these events.
+    let mut completed = None;
+    while let Ok(event) = app.rx.try_recv() {
+        match event {
+            ScanEvent::Error(error) => app.fail_scan(error),
+            ScanEvent::Finished { .. } => completed = Some(app.complete_scan()),
+            _ => {}
+        }
+    }
+
+    assert_eq!(completed, Some(false));
+    assert!(!app.is_scanning);
+    assert_eq!(app.scan_jobs[0].outcome.as_deref(), Some("failed"));
+    assert!(app.last_summary.is_none(), "no summary popup over the failure");
+    assert!(!app.log_events.iter().any(|l| l.starts_with("Scan finished")));
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Status: FAILED"));
+}
+
+#[test]
+fn search_filters_by_service_or_banner() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for (port, service, banner) in [(22, "ssh", Some("SSH-2.0-OpenSSH_9.6")), (80, "http", Some("200 OK nginx/1.25")), (8080, "http-alt", None)] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: "open".to_string(),
+            service: service.to_string(),
+            response_ms: 1,
+            banner: banner.map(str::to_string),
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    app.selected = Some(0);
+    app.set_filter("NGINX".to_string());
+    assert_eq!(app.visible_rows(), vec![1]);
+    assert_eq!(app.selected, None, "the hidden selection is dropped");
+    app.set_filter("http".to_string());
+    assert_eq!(app.visible_rows(), vec![1, 2]);
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Results (matching \"http\")"));
+    app.filter_editing = true;
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("Search: http_"));
+    assert_eq!(app.mode_label(), "SEARCH results");
+
+    app.set_filter(String::new());
+    assert_eq!(app.visible_rows(), vec![0, 1, 2]);
+}
+
+#[test]
+fn last_search_is_kept_and_steps_through_matches() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    for (port, service) in [(22, "ssh"), (80, "http"), (443, "https"), (8080, "http-alt")] {
+        app.results.push(ScanResult {
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: "open".to_string(),
+            service: service.to_string(),
+            response_ms: 1,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    assert!(!app.jump_to_match(true), "nothing to repeat before a search");
+
+    app.filter_editing = true;
+    app.set_filter("http".to_string());
+    app.confirm_filter();
+    assert_eq!(app.last_query, "http");
+    // Editing the search away keeps the last query for stepping through the full table.
+    app.set_filter(String::new());
+    app.confirm_filter();
+    assert_eq!(app.last_query, "http");
+
+    let mut ports = Vec::new();
+    for _ in 0..4 {
+        assert!(app.jump_to_match(true));
+        ports.push(app.detail_result().unwrap().port);
+    }
+    assert_eq!(ports, vec![80, 443, 8080, 80], "wraps after the last match");
+    assert!(app.jump_to_match(false));
+    assert_eq!(app.detail_result().unwrap().port, 8080);
+
+    app.reset_filter();
+    assert!(app.last_query.is_empty());
+    assert!(!app.jump_to_match(true));
+}
+
+#[test]
+fn rescan_shows_cached_rows_as_stale_until_fresh_results_replace_them() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    let row = |port, status: &str, service: &str, ms| ScanResult {
+        host: "10.0.0.1".to_string(),
+        port,
+        protocol: Protocol::Tcp,
+        status: status.to_string(),
+        service: service.to_string(),
+        response_ms: ms,
+        banner: None,
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: None,
+    };
+    app.begin_scan("10.0.0.1");
+    for r in [row(22, "open", "ssh", 10), row(23, "closed", "telnet", 3), row(80, "open", "http", 20), row(443, "open", "https", 30)] {
+        app.apply_result(r);
+    }
+    app.finish_scan();
+
+    // The rescan starts with every cached row, all stale.
+    app.begin_scan("10.0.0.1");
+    assert_eq!(app.results.len(), 4);
+    assert!(app.results.iter().all(|r| app.is_stale(r)));
+
+    // Fresh results replace their cached rows in place.
+    app.apply_result(row(22, "open", "ssh", 12));
+    app.apply_result(row(80, "closed", "http", 4));
+    assert_eq!(app.results.len(), 4);
+    let fresh: Vec<(u16, &str)> = app.results.iter().filter(|r| !app.is_stale(r)).map(|r| (r.port, r.status.as_str())).collect();
+    assert_eq!(fresh, [(22, "open"), (80, "closed")]);
+    assert_eq!(app.results.iter().find(|r| r.port == 22).unwrap().response_ms, 12);
+
+    // The summary counts only what this scan has seen, not the stale 23 and 443.
+    let summary = app.scan_summary();
+    assert_eq!((summary.open, summary.closed, summary.filtered, summary.other), (1, 1, 0, 0));
+    assert_eq!(summary.open_ports, vec!["22/tcp ssh"]);
+
+    // Finishing drops cached rows the scan never reached.
+    app.finish_scan();
+    let ports: Vec<u16> = app.results.iter().map(|r| r.port).collect();
+    assert_eq!(ports, [22, 80]);
+}
+
+#[test]
+fn completed_scan_summary_counts_states_and_lists_open_ports() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.begin_scan("10.0.0.1");
+    app.start_job("10.0.0.1", "ports 1-1000");
+    for (port, status, service, ms) in [(22, "open", "ssh", 10), (80, "open", "http", 20), (23, "closed", "telnet", 3), (25, "filtered", "smtp", 2000), (81, "reset", "unknown", 5)] {
+        app.apply_result(ScanResult {
+            host: "10.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+            status: status.to_string(),
+            service: service.to_string(),
+            response_ms: ms,
+            banner: None,
+            family: None,
+            read_timed_out: false,
+            error: None,
+            attempts: 1,
+            software: None,
+        });
+    }
+    app.total_scanned = 5;
+
+    let summary = app.scan_summary();
+    assert_eq!((summary.scanned, summary.open, summary.closed, summary.filtered, summary.other), (5, 2, 1, 1, 1));
+    assert_eq!(summary.avg_response_ms, Some(11));
+    assert_eq!(summary.open_ports, vec!["22/tcp ssh", "80/tcp http"]);
+
+    app.last_summary = Some(summary);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    let text = screen_text(&terminal);
+    assert!(text.contains("Scan summary"));
+    assert!(text.contains("10.0.0.1 (ports 1-1000)"));
+    assert!(text.contains("2 open, 1 closed, 1 filtered, 1 other"));
+    assert!(text.contains("Avg response  11ms"));
+}
+
+#[test]
+fn counters_track_response_times_of_answered_ports() {
+    let (_tx, rx) = mpsc::channel::<ScanEvent>(1);
+    let mut app = App::new(rx);
+    app.begin_scan("10.0.0.1");
+    let result = |port, status: &str, response_ms| ScanResult {
+        host: "10.0.0.1".to_string(),
+        port,
+        protocol: Protocol::Tcp,
+        status: status.to_string(),
+        service: "unknown".to_string(),
+        response_ms,
+        banner: None,
+        family: None,
+        read_timed_out: false,
+        error: None,
+        attempts: 1,
+        software: None,
+    };
+    assert_eq!(app.latency.min_avg_max(), None);
+    app.apply_result(result(22, "open", 4));
+    app.apply_result(result(80, "open", 20));
+    app.apply_result(result(23, "closed", 1));
+    app.apply_result(result(25, "filtered", 3000));
+    assert_eq!(app.latency.min_avg_max(), Some((4, 12, 20)));
+
+    let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+    terminal.draw(|f| ui::draw(f, &app)).unwrap();
+    assert!(screen_text(&terminal).contains("4/12/20ms"));
+
+    app.latency_closed = true;
+    app.begin_scan("10.0.0.1");
+    app.apply_result(result(22, "open", 4));
+    app.apply_result(result(23, "closed", 1));
+    assert_eq!(app.latency.min_avg_max(), Some((1, 2, 4)));
+}